@@ -1,35 +1,256 @@
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    fs::{self, File, TryLockError},
+    io::ErrorKind,
+    path::PathBuf,
+    pin::Pin,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use regex::Regex;
-use testcontainers::{runners::AsyncRunner, GenericImage, ImageExt};
-use tokio::time::sleep;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use testcontainers::{
+    bollard::{
+        query_parameters::{
+            CreateImageOptionsBuilder, ListContainersOptionsBuilder, RemoveContainerOptionsBuilder,
+        },
+        Docker,
+    },
+    core::Host,
+    runners::AsyncRunner,
+    ContainerRequest, GenericImage, ImageExt,
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt},
+    sync::mpsc,
+    time::sleep,
+};
 
 type CloudflaredContainer = testcontainers::ContainerAsync<GenericImage>;
 
-pub async fn start_cloudflared(target_port: u16) -> Result<(CloudflaredContainer, String), String> {
-    let image = GenericImage::new("cloudflare/cloudflared", "latest")
+const CLOUDFLARED_IMAGE_NAME: &str = "cloudflare/cloudflared";
+const CLOUDFLARED_IMAGE_TAG: &str = "2025.8.1";
+const LABEL_APP: &str = "com.slowlyunhinged.app";
+const LABEL_INSTANCE: &str = "com.slowlyunhinged.instance";
+const LABEL_STARTED_AT: &str = "com.slowlyunhinged.started-at";
+const APP_LABEL_VALUE: &str = "com.slowlyunhinged.agent";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerImage {
+    pub name: String,
+    pub tag: String,
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+impl ContainerImage {
+    pub fn cloudflared() -> Self {
+        Self {
+            name: CLOUDFLARED_IMAGE_NAME.to_string(),
+            tag: CLOUDFLARED_IMAGE_TAG.to_string(),
+            digest: None,
+        }
+    }
+
+    /// Tag passed to testcontainers; a pinned digest rides along as `tag@digest`.
+    fn reference_tag(&self) -> String {
+        match self.digest.as_deref().map(str::trim) {
+            Some(digest) if !digest.is_empty() => format!("{}@{digest}", self.tag),
+            _ => self.tag.clone(),
+        }
+    }
+
+    pub fn descriptor(&self) -> String {
+        format!("{}:{}", self.name, self.reference_tag())
+    }
+
+    fn generic_image(&self) -> GenericImage {
+        GenericImage::new(self.name.clone(), self.reference_tag())
+    }
+}
+
+/// How the tunnel container reaches the companion HTTP server running on the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HostAccess {
+    /// Docker Desktop resolves `host.docker.internal` without any extra configuration.
+    Builtin,
+    /// Native Linux engines (20.10+) need an explicit `host-gateway` mapping.
+    HostGateway,
+    /// Older native engines share the host network namespace instead.
+    HostNetwork,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug)]
+pub struct LogLine {
+    pub stream: LogStream,
+    pub text: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedContainer {
+    pub id: String,
+    pub name: String,
+    pub instance_id: Option<String>,
+    pub started_at: Option<u64>,
+    pub state: String,
+    /// Why the container could not be removed; `None` once it is gone.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagePullProgress {
+    pub image: String,
+    pub status: String,
+    pub current_bytes: u64,
+    pub total_bytes: u64,
+    pub done: bool,
+}
+
+/// Generates an identifier that distinguishes this app run from previous ones.
+pub fn new_instance_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    format!("{nanos:x}-{:x}", std::process::id())
+}
+
+/// Where each running instance holds a lock on `<instance id>.lock`, so that cleanup can tell
+/// the containers of a crashed run from those of another instance that is still running.
+fn instance_lock_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("{APP_LABEL_VALUE}.instances"))
+}
+
+fn instance_lock_path(instance_id: &str) -> Option<PathBuf> {
+    // The id comes from a container label, so it must not be able to name another path.
+    let valid = !instance_id.is_empty()
+        && instance_id
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c == '-');
+    valid.then(|| instance_lock_dir().join(format!("{instance_id}.lock")))
+}
+
+/// Marks `instance_id` as running for as long as the returned file stays open. The operating
+/// system releases the lock when the process exits, including when it crashes.
+pub fn hold_instance_lock(instance_id: &str) -> Option<File> {
+    let path = instance_lock_path(instance_id)?;
+    let result = fs::create_dir_all(instance_lock_dir()).and_then(|_| {
+        let file = File::create(&path)?;
+        file.lock()?;
+        Ok(file)
+    });
+    match result {
+        Ok(file) => Some(file),
+        Err(err) => {
+            eprintln!(
+                "[Docker] Could not mark this instance as running ({err}); another instance may remove its tunnel."
+            );
+            None
+        }
+    }
+}
+
+/// Whether the instance that labelled a container still holds its lock. Containers from runs
+/// that predate the lock files have none and count as orphaned.
+fn instance_is_running(instance_id: &str) -> bool {
+    let Some(path) = instance_lock_path(instance_id) else {
+        return false;
+    };
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return false,
+        Err(err) => {
+            eprintln!("[Docker] Could not check instance {instance_id}: {err}");
+            return true;
+        }
+    };
+    match file.try_lock() {
+        Ok(()) => {
+            drop(file);
+            let _ = fs::remove_file(&path);
+            false
+        }
+        Err(TryLockError::WouldBlock) => true,
+        Err(TryLockError::Error(err)) => {
+            eprintln!("[Docker] Could not check instance {instance_id}: {err}");
+            true
+        }
+    }
+}
+
+fn with_companion_labels(
+    request: ContainerRequest<GenericImage>,
+    instance_id: &str,
+) -> ContainerRequest<GenericImage> {
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    request.with_labels([
+        (LABEL_APP, APP_LABEL_VALUE.to_string()),
+        (LABEL_INSTANCE, instance_id.to_string()),
+        (LABEL_STARTED_AT, started_at.to_string()),
+    ])
+}
+
+/// Starts the tunnel container; the caller follows its logs to learn the public URL.
+pub async fn start_cloudflared(
+    image: &ContainerImage,
+    target_port: u16,
+    instance_id: &str,
+) -> Result<CloudflaredContainer, String> {
+    ensure_image_present(image).await?;
+
+    let host_access = detect_host_access().await;
+    let target_host = match host_access {
+        HostAccess::HostNetwork => "127.0.0.1",
+        HostAccess::Builtin | HostAccess::HostGateway => "host.docker.internal",
+    };
+    println!("[Docker] Tunnel reaches the companion via {host_access:?} at {target_host}.");
+
+    let request = image
+        .generic_image()
         .with_entrypoint("cloudflared")
         .with_cmd([
             "tunnel".to_string(),
             "--url".to_string(),
-            format!("http://host.docker.internal:{target_port}"),
+            format!("http://{target_host}:{target_port}"),
         ]);
+    let request = with_companion_labels(request, instance_id);
+    let request = match host_access {
+        HostAccess::Builtin => request,
+        HostAccess::HostGateway => request.with_host("host.docker.internal", Host::HostGateway),
+        HostAccess::HostNetwork => request.with_network("host"),
+    };
 
-    let container = image
+    request
         .start()
         .await
-        .map_err(|err| format!("Failed to launch cloudflared: {err}"))?;
-
-    let tunnel_url = wait_for_tunnel_url(&container).await?;
-    Ok((container, tunnel_url))
+        .map_err(|err| format!("Failed to launch cloudflared: {err}"))
 }
 
-pub async fn verify_cloudflared_container() -> Result<(), String> {
-    let image = GenericImage::new("cloudflare/cloudflared", "latest")
+pub async fn verify_cloudflared_container(
+    image: &ContainerImage,
+    instance_id: &str,
+) -> Result<(), String> {
+    ensure_image_present(image).await?;
+
+    let request = image
+        .generic_image()
         .with_entrypoint("cloudflared")
         .with_cmd(vec!["--version".to_string()]);
 
-    let container = image
+    let container = with_companion_labels(request, instance_id)
         .start()
         .await
         .map_err(|err| format!("Failed to start test cloudflared container: {err}"))?;
@@ -45,59 +266,264 @@ pub async fn verify_cloudflared_container() -> Result<(), String> {
     Ok(())
 }
 
-async fn wait_for_tunnel_url(container: &CloudflaredContainer) -> Result<String, String> {
-    let url_regex = Regex::new(r"(https://[a-zA-Z0-9-]+\.trycloudflare\.com)")
-        .map_err(|err| err.to_string())?;
+/// Force-removes containers labelled by earlier runs of the companion that were never
+/// cleaned up, typically because the app crashed while a tunnel was running. Containers of
+/// other instances that are still running are left alone. A container that cannot be removed
+/// is reported with its error and the rest are still tried.
+pub async fn remove_orphaned_containers(
+    current_instance_id: &str,
+) -> Result<Vec<OrphanedContainer>, String> {
+    let docker = docker_client()?;
+    let filters = HashMap::from([("label", vec![format!("{LABEL_APP}={APP_LABEL_VALUE}")])]);
+    let options = ListContainersOptionsBuilder::new()
+        .all(true)
+        .filters(&filters)
+        .build();
+    let containers = docker
+        .list_containers(Some(options))
+        .await
+        .map_err(|err| format!("Failed to list companion containers: {err}"))?;
+
+    let mut orphans = Vec::new();
+    for summary in containers {
+        let labels = summary.labels.unwrap_or_default();
+        let instance_id = labels.get(LABEL_INSTANCE).cloned();
+        if let Some(instance_id) = instance_id.as_deref() {
+            if instance_id == current_instance_id || instance_is_running(instance_id) {
+                continue;
+            }
+        }
+        let Some(id) = summary.id else {
+            continue;
+        };
 
-    let mut last_snapshot = String::new();
+        let name = summary
+            .names
+            .and_then(|names| names.into_iter().next())
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| id.chars().take(12).collect());
+        let options = RemoveContainerOptionsBuilder::new()
+            .force(true)
+            .v(true)
+            .build();
+        let error = match docker.remove_container(&id, Some(options)).await {
+            Ok(()) => {
+                println!("[Docker] Removed orphaned container {name} ({id}).");
+                None
+            }
+            Err(err) => {
+                eprintln!("[Docker] Failed to remove orphaned container {name} ({id}): {err}");
+                Some(err.to_string())
+            }
+        };
+        orphans.push(OrphanedContainer {
+            id,
+            name,
+            instance_id,
+            started_at: labels
+                .get(LABEL_STARTED_AT)
+                .and_then(|value| value.parse().ok()),
+            state: summary
+                .state
+                .map(|state| state.to_string())
+                .unwrap_or_default(),
+            error,
+        });
+    }
 
-    for attempt in 0..60 {
-        let stdout = container
-            .stdout_to_vec()
-            .await
-            .map_err(|err| format!("Failed to read cloudflared stdout: {err}"))?;
-        let stderr = container
-            .stderr_to_vec()
-            .await
-            .map_err(|err| format!("Failed to read cloudflared stderr: {err}"))?;
+    Ok(orphans)
+}
 
-        let combined = format!(
-            "{}{}",
-            String::from_utf8_lossy(&stdout),
-            String::from_utf8_lossy(&stderr)
-        );
+/// Only native Linux engines lack `host.docker.internal`; Docker Desktop provides it on
+/// every platform, including Linux.
+async fn detect_host_access() -> HostAccess {
+    if !cfg!(target_os = "linux") {
+        return HostAccess::Builtin;
+    }
 
-        if let Some(capture) = url_regex.captures(&combined).and_then(|c| c.get(1)) {
-            return Ok(capture.as_str().to_string());
+    let docker = match docker_client() {
+        Ok(docker) => docker,
+        Err(err) => {
+            eprintln!("[Docker] {err}. Assuming a native Linux engine.");
+            return HostAccess::HostGateway;
         }
+    };
 
-        if attempt == 0 || combined.len() != last_snapshot.len() {
-            println!(
-                "[cloudflared] Waiting for tunnel URL (attempt {}): {} bytes of logs.",
-                attempt + 1,
-                combined.len()
-            );
-            last_snapshot = combined;
+    if let Ok(info) = docker.info().await {
+        let operating_system = info.operating_system.unwrap_or_default();
+        if operating_system.contains("Docker Desktop") {
+            return HostAccess::Builtin;
         }
+    }
+
+    let supports_host_gateway = match docker.version().await {
+        Ok(version) => version
+            .version
+            .as_deref()
+            .and_then(parse_major_minor)
+            .map(|version| version >= (20, 10))
+            .unwrap_or(true),
+        Err(err) => {
+            eprintln!("[Docker] Failed to query engine version: {err}");
+            true
+        }
+    };
 
-        sleep(Duration::from_millis(500)).await;
+    if supports_host_gateway {
+        HostAccess::HostGateway
+    } else {
+        HostAccess::HostNetwork
     }
+}
 
-    let stdout = container.stdout_to_vec().await.unwrap_or_default();
-    let stderr = container.stderr_to_vec().await.unwrap_or_default();
+fn parse_major_minor(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts
+        .next()?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    Some((major, minor))
+}
 
-    let mut combined = stdout;
-    combined.extend_from_slice(&stderr);
+fn docker_client() -> Result<Docker, String> {
+    Docker::connect_with_defaults().map_err(|err| format!("Failed to connect to Docker: {err}"))
+}
 
-    let tail = String::from_utf8_lossy(&combined);
-    let snippet = if tail.len() > 512 {
-        format!("{}…", &tail[..512])
-    } else {
-        tail.to_string()
-    };
+async fn image_is_present(docker: &Docker, image: &ContainerImage) -> Result<bool, String> {
+    match docker.inspect_image(&image.descriptor()).await {
+        Ok(_) => Ok(true),
+        Err(testcontainers::bollard::errors::Error::DockerResponseServerError {
+            status_code: 404,
+            ..
+        }) => Ok(false),
+        Err(err) => Err(format!(
+            "Failed to inspect image \"{}\": {err}",
+            image.descriptor()
+        )),
+    }
+}
+
+/// Pulls `image` if it is missing, so that a failed pull reports an actionable message
+/// instead of the error from testcontainers' implicit pull.
+async fn ensure_image_present(image: &ContainerImage) -> Result<(), String> {
+    let docker = docker_client()?;
+    if image_is_present(&docker, image).await? {
+        return Ok(());
+    }
+
+    println!(
+        "[Docker] Image {} is not present locally. Pulling…",
+        image.descriptor()
+    );
+    pull_image(&docker, image, |_| {}).await
+}
+
+/// Pulls `image` unless it is already present, reporting aggregated layer progress.
+pub async fn prepare_image(
+    image: &ContainerImage,
+    mut on_progress: impl FnMut(ImagePullProgress),
+) -> Result<(), String> {
+    let docker = docker_client()?;
+    if image_is_present(&docker, image).await? {
+        on_progress(ImagePullProgress {
+            image: image.descriptor(),
+            status: "Image already present".to_string(),
+            current_bytes: 0,
+            total_bytes: 0,
+            done: true,
+        });
+        return Ok(());
+    }
+
+    pull_image(&docker, image, &mut on_progress).await?;
+    on_progress(ImagePullProgress {
+        image: image.descriptor(),
+        status: "Pull complete".to_string(),
+        current_bytes: 0,
+        total_bytes: 0,
+        done: true,
+    });
+    Ok(())
+}
+
+async fn pull_image(
+    docker: &Docker,
+    image: &ContainerImage,
+    mut on_progress: impl FnMut(ImagePullProgress),
+) -> Result<(), String> {
+    let descriptor = image.descriptor();
+    let options = CreateImageOptionsBuilder::new()
+        .from_image(&descriptor)
+        .build();
+    let mut stream = docker.create_image(Some(options), None, None);
+    let mut layers: HashMap<String, (u64, u64)> = HashMap::new();
+
+    while let Some(item) = stream.next().await {
+        let info = item.map_err(|err| {
+            format!(
+                "Container image \"{descriptor}\" is not available locally and could not be pulled: {err}. \
+                 If this machine is offline, connect to the internet and retry, \
+                 or load the image manually with `docker pull {descriptor}`."
+            )
+        })?;
 
-    Err(format!(
-        "Timed out waiting for cloudflared tunnel URL. Latest logs:\n{}",
-        snippet
-    ))
+        if let (Some(layer), Some(detail)) = (info.id.as_ref(), info.progress_detail.as_ref()) {
+            if let (Some(current), Some(total)) = (detail.current, detail.total) {
+                layers.insert(layer.clone(), (current.max(0) as u64, total.max(0) as u64));
+            }
+        }
+
+        let (current_bytes, total_bytes) = layers
+            .values()
+            .fold((0, 0), |(current, total), (c, t)| (current + c, total + t));
+
+        on_progress(ImagePullProgress {
+            image: descriptor.clone(),
+            status: info.status.unwrap_or_default(),
+            current_bytes,
+            total_bytes,
+            done: false,
+        });
+    }
+
+    println!("[Docker] Pulled image {descriptor}.");
+    Ok(())
+}
+
+/// Follows stdout and stderr of `container` until it stops, yielding one item per log line.
+pub fn follow_logs(container: &CloudflaredContainer) -> mpsc::UnboundedReceiver<LogLine> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    spawn_line_reader(container.stdout(true), LogStream::Stdout, sender.clone());
+    spawn_line_reader(container.stderr(true), LogStream::Stderr, sender);
+    receiver
+}
+
+fn spawn_line_reader(
+    reader: Pin<Box<dyn AsyncBufRead + Send>>,
+    stream: LogStream,
+    sender: mpsc::UnboundedSender<LogLine>,
+) {
+    tokio::spawn(async move {
+        let mut lines = reader.lines();
+        loop {
+            match lines.next_line().await {
+                Ok(Some(text)) => {
+                    if sender.send(LogLine { stream, text }).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    if err.kind() != ErrorKind::UnexpectedEof {
+                        eprintln!("[cloudflared] Log stream ({stream:?}) failed: {err}");
+                    }
+                    break;
+                }
+            }
+        }
+    });
 }
@@ -0,0 +1,142 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tauri::{async_runtime::JoinHandle, AppHandle, Emitter};
+use tokio::time::sleep;
+
+use crate::{
+    hub::{AgentRegistration, HeartbeatOutcome, HubClient},
+    register_with_hub, AppState,
+};
+
+const HUB_STATUS_EVENT: &str = "hub-status";
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum HubConnectivity {
+    /// No agent is registered.
+    Idle,
+    /// The last heartbeat reached the Hub and the registration is live.
+    Connected,
+    /// The Hub could not be reached; the registration may still be live.
+    Degraded,
+    /// The Hub dropped the registration and re-registering failed.
+    Lost,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HubStatus {
+    connectivity: HubConnectivity,
+    detail: Option<String>,
+    last_success_ms: Option<u64>,
+}
+
+impl HubStatus {
+    pub(crate) fn idle() -> Self {
+        Self {
+            connectivity: HubConnectivity::Idle,
+            detail: None,
+            last_success_ms: None,
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+async fn publish(
+    app: &AppHandle,
+    state: &AppState,
+    connectivity: HubConnectivity,
+    detail: Option<String>,
+) {
+    let status = {
+        let mut guard = state.hub_status.lock().await;
+        let changed = guard.connectivity != connectivity || guard.detail != detail;
+        if connectivity == HubConnectivity::Connected {
+            guard.last_success_ms = Some(now_ms());
+        }
+        guard.connectivity = connectivity;
+        guard.detail = detail;
+        if !changed {
+            return;
+        }
+        guard.clone()
+    };
+
+    if let Err(err) = app.emit(HUB_STATUS_EVENT, &status) {
+        eprintln!("[Hub] Failed to emit Hub status: {err}");
+    }
+}
+
+/// Keeps the Hub registration alive until the returned task is aborted.
+pub(crate) fn spawn(
+    app: AppHandle,
+    state: Arc<AppState>,
+    hub: HubClient,
+    interval: Duration,
+    registration: AgentRegistration,
+) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        publish(&app, &state, HubConnectivity::Connected, None).await;
+
+        loop {
+            sleep(interval).await;
+
+            match hub.heartbeat(&registration).await {
+                Ok(HeartbeatOutcome::Alive) => {
+                    publish(&app, &state, HubConnectivity::Connected, None).await;
+                }
+                Ok(HeartbeatOutcome::RegistrationGone) => {
+                    println!(
+                        "[Hub] Registration for \"{}\" is gone. Re-registering…",
+                        registration.screen_name
+                    );
+                    match register_with_hub(&app, &state, &hub, &registration).await {
+                        Ok(()) => {
+                            publish(
+                                &app,
+                                &state,
+                                HubConnectivity::Connected,
+                                Some(
+                                    "Registration had expired on the Hub; re-registered."
+                                        .to_string(),
+                                ),
+                            )
+                            .await;
+                        }
+                        Err(err) => {
+                            eprintln!("[Hub] Re-registration failed: {err}");
+                            publish(&app, &state, HubConnectivity::Lost, Some(err)).await;
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("[Hub] Heartbeat failed: {err}");
+                    publish(
+                        &app,
+                        &state,
+                        HubConnectivity::Degraded,
+                        Some(err.to_string()),
+                    )
+                    .await;
+                }
+            }
+        }
+    })
+}
+
+pub(crate) async fn stop(app: &AppHandle, state: &Arc<AppState>) {
+    if let Some(task) = state.heartbeat_task.lock().await.take() {
+        task.abort();
+    }
+    publish(app, state, HubConnectivity::Idle, None).await;
+}
@@ -0,0 +1,432 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::identity::{AgentIdentity, RequestSignature, SignedAction};
+
+pub(crate) const DEFAULT_HUB_URL: &str =
+    "https://slowlyunhinged-hub-54127830651.us-central1.run.app";
+pub(crate) const DEFAULT_HUB_REGISTER_PATH: &str = "/api/register-agent";
+pub(crate) const DEFAULT_HUB_UNREGISTER_PATH: &str = "/api/unregister-agent";
+pub(crate) const DEFAULT_HUB_HEARTBEAT_PATH: &str = "/api/agent-heartbeat";
+pub(crate) const DEFAULT_HUB_RELEASE_IDENTITY_PATH: &str = "/api/release-identity";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Registration waits for the Hub to deliver its default key over the tunnel.
+const REGISTER_TIMEOUT: Duration = Duration::from_secs(60);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+const UNREGISTER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HubEndpoints {
+    pub(crate) base_url: String,
+    register_path: String,
+    unregister_path: String,
+    heartbeat_path: String,
+    release_identity_path: String,
+}
+
+impl HubEndpoints {
+    /// Validates user-supplied overrides, falling back to the public Hub and default paths.
+    pub(crate) fn resolve(
+        base_url: Option<(&str, &str)>,
+        register_path: Option<&str>,
+        unregister_path: Option<&str>,
+        heartbeat_path: Option<&str>,
+        release_identity_path: Option<&str>,
+    ) -> Result<Self, String> {
+        let base_url = match base_url {
+            Some((value, source)) => validate_base_url(value, source)?,
+            None => DEFAULT_HUB_URL.to_string(),
+        };
+
+        Ok(Self {
+            base_url,
+            register_path: validate_path(
+                register_path,
+                "hubRegisterPath",
+                DEFAULT_HUB_REGISTER_PATH,
+            )?,
+            unregister_path: validate_path(
+                unregister_path,
+                "hubUnregisterPath",
+                DEFAULT_HUB_UNREGISTER_PATH,
+            )?,
+            heartbeat_path: validate_path(
+                heartbeat_path,
+                "hubHeartbeatPath",
+                DEFAULT_HUB_HEARTBEAT_PATH,
+            )?,
+            release_identity_path: validate_path(
+                release_identity_path,
+                "hubReleaseIdentityPath",
+                DEFAULT_HUB_RELEASE_IDENTITY_PATH,
+            )?,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+pub(crate) fn validate_base_url(raw: &str, source: &str) -> Result<String, String> {
+    let trimmed = raw.trim().trim_end_matches('/');
+    let parsed = url::Url::parse(trimmed)
+        .map_err(|err| format!("Invalid Hub URL \"{raw}\" from {source}: {err}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!(
+            "Invalid Hub URL \"{raw}\" from {source}: expected an http:// or https:// URL with a host."
+        ));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(format!(
+            "Invalid Hub URL \"{raw}\" from {source}: query strings and fragments are not supported."
+        ));
+    }
+    Ok(trimmed.to_string())
+}
+
+pub(crate) fn validate_path(
+    raw: Option<&str>,
+    field: &str,
+    default: &str,
+) -> Result<String, String> {
+    let Some(raw) = raw.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(default.to_string());
+    };
+    if !raw.starts_with('/') || raw.contains(['?', '#']) || raw.contains(char::is_whitespace) {
+        return Err(format!(
+            "Invalid \"{field}\" in settings: \"{raw}\" must be an absolute path such as \"{default}\"."
+        ));
+    }
+    Ok(raw.to_string())
+}
+
+#[derive(Clone)]
+pub(crate) struct AgentRegistration {
+    pub(crate) screen_name: String,
+    pub(crate) tunnel_url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisterAgentPayload<'a> {
+    screen_name: &'a str,
+    tunnel_url: &'a str,
+    requires_nanobanana_key: bool,
+    has_local_nanobanana_key: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zoom_access_token: Option<&'a str>,
+    #[serde(flatten)]
+    signature: RequestSignature,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentPayload<'a> {
+    screen_name: &'a str,
+    tunnel_url: &'a str,
+    #[serde(flatten)]
+    signature: RequestSignature,
+}
+
+#[derive(Deserialize)]
+struct HubErrorBody {
+    error: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HubError {
+    /// The Hub could not be reached at all (DNS, connection, or timeout).
+    Network { url: String, detail: String },
+    /// The Hub understood the request and refused it (4xx).
+    Rejected { status: u16, message: String },
+    /// The screen name is bound to a different agent identity.
+    IdentityConflict { message: String },
+    /// The Hub answered with a server error or asked us to back off (5xx, 429).
+    Unavailable { status: u16, message: String },
+}
+
+impl fmt::Display for HubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HubError::Network { url, detail } => write!(
+                f,
+                "Could not reach the Hub at {url} ({detail}). \
+                 Check your internet connection and the configured Hub URL."
+            ),
+            HubError::Rejected { status, message } => write!(
+                f,
+                "The Hub rejected the request (HTTP {status}): {message}. \
+                 Check your screen name and settings, then try again."
+            ),
+            HubError::IdentityConflict { message } => write!(
+                f,
+                "The Hub refused the registration: {message}. \
+                 This screen name belongs to another companion. If it is yours, reset the \
+                 identity on the companion that claimed it, or pick a different screen name."
+            ),
+            HubError::Unavailable { status, message } => write!(
+                f,
+                "The Hub is temporarily unavailable (HTTP {status}): {message}. \
+                 Wait a few minutes and try again."
+            ),
+        }
+    }
+}
+
+impl From<HubError> for String {
+    fn from(err: HubError) -> Self {
+        err.to_string()
+    }
+}
+
+pub(crate) enum HeartbeatOutcome {
+    Alive,
+    RegistrationGone,
+}
+
+#[derive(Clone, Copy)]
+struct RetryPolicy {
+    attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    const REGISTER: Self = Self {
+        attempts: 4,
+        initial_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(8),
+    };
+    const HEARTBEAT: Self = Self {
+        attempts: 3,
+        initial_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(4),
+    };
+    // Unregistering happens on shutdown, so it gets a single quick retry.
+    const UNREGISTER: Self = Self {
+        attempts: 2,
+        initial_delay: Duration::from_millis(250),
+        max_delay: Duration::from_millis(250),
+    };
+
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    let text = response.text().await.unwrap_or_default();
+    serde_json::from_str::<HubErrorBody>(&text)
+        .ok()
+        .and_then(|body| body.error.or(body.message))
+        .unwrap_or_else(|| {
+            let trimmed = text.trim();
+            if trimmed.is_empty() {
+                "no details provided".to_string()
+            } else {
+                trimmed.chars().take(200).collect()
+            }
+        })
+}
+
+async fn into_error(response: reqwest::Response) -> HubError {
+    let status = response.status();
+    let message = error_message(response).await;
+    if is_transient(status) {
+        HubError::Unavailable {
+            status: status.as_u16(),
+            message,
+        }
+    } else {
+        HubError::Rejected {
+            status: status.as_u16(),
+            message,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HubClient {
+    http: reqwest::Client,
+    endpoints: HubEndpoints,
+    identity: Arc<AgentIdentity>,
+}
+
+impl HubClient {
+    pub(crate) fn new(
+        endpoints: HubEndpoints,
+        identity: Arc<AgentIdentity>,
+    ) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .map_err(|err| format!("Failed to build Hub HTTP client: {err}"))?;
+        Ok(Self {
+            http,
+            endpoints,
+            identity,
+        })
+    }
+
+    fn agent_payload<'a>(
+        &self,
+        action: SignedAction,
+        registration: &'a AgentRegistration,
+    ) -> AgentPayload<'a> {
+        AgentPayload {
+            screen_name: &registration.screen_name,
+            tunnel_url: &registration.tunnel_url,
+            signature: self.identity.sign(
+                action,
+                &registration.screen_name,
+                &registration.tunnel_url,
+            ),
+        }
+    }
+
+    pub(crate) fn endpoints(&self) -> &HubEndpoints {
+        &self.endpoints
+    }
+
+    pub(crate) async fn register(
+        &self,
+        registration: &AgentRegistration,
+        has_local_nanobanana_key: bool,
+        zoom_access_token: Option<&str>,
+    ) -> Result<(), HubError> {
+        let payload = || RegisterAgentPayload {
+            screen_name: &registration.screen_name,
+            tunnel_url: &registration.tunnel_url,
+            requires_nanobanana_key: true,
+            has_local_nanobanana_key,
+            zoom_access_token,
+            signature: self.identity.sign(
+                SignedAction::Register,
+                &registration.screen_name,
+                &registration.tunnel_url,
+            ),
+        };
+        let url = self.endpoints.url(&self.endpoints.register_path);
+        let response = self
+            .post(&url, payload, REGISTER_TIMEOUT, RetryPolicy::REGISTER)
+            .await?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::CONFLICT => Err(HubError::IdentityConflict {
+                message: error_message(response).await,
+            }),
+            _ => Err(into_error(response).await),
+        }
+    }
+
+    pub(crate) async fn unregister(
+        &self,
+        registration: &AgentRegistration,
+    ) -> Result<(), HubError> {
+        let url = self.endpoints.url(&self.endpoints.unregister_path);
+        let payload = || self.agent_payload(SignedAction::Unregister, registration);
+        let response = self
+            .post(&url, payload, UNREGISTER_TIMEOUT, RetryPolicy::UNREGISTER)
+            .await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(into_error(response).await)
+        }
+    }
+
+    pub(crate) async fn heartbeat(
+        &self,
+        registration: &AgentRegistration,
+    ) -> Result<HeartbeatOutcome, HubError> {
+        let url = self.endpoints.url(&self.endpoints.heartbeat_path);
+        let payload = || self.agent_payload(SignedAction::Heartbeat, registration);
+        let response = self
+            .post(&url, payload, HEARTBEAT_TIMEOUT, RetryPolicy::HEARTBEAT)
+            .await?;
+        match response.status() {
+            status if status.is_success() => Ok(HeartbeatOutcome::Alive),
+            StatusCode::NOT_FOUND => Ok(HeartbeatOutcome::RegistrationGone),
+            _ => Err(into_error(response).await),
+        }
+    }
+
+    /// Asks the Hub to drop this identity's claim on `screen_name` so a new key can take it.
+    pub(crate) async fn release_identity(&self, screen_name: &str) -> Result<(), HubError> {
+        let url = self.endpoints.url(&self.endpoints.release_identity_path);
+        let payload = || AgentPayload {
+            screen_name,
+            tunnel_url: "",
+            signature: self.identity.sign(SignedAction::Release, screen_name, ""),
+        };
+        let response = self
+            .post(&url, payload, UNREGISTER_TIMEOUT, RetryPolicy::UNREGISTER)
+            .await?;
+        if response.status().is_success() || response.status() == StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(into_error(response).await)
+        }
+    }
+
+    /// Sends the payload built by `body`, retrying network failures and transient statuses
+    /// with exponential backoff. Each attempt is signed afresh because the Hub rejects
+    /// replayed signatures. Any other response is returned to the caller for interpretation.
+    async fn post<T: Serialize>(
+        &self,
+        url: &str,
+        body: impl Fn() -> T,
+        timeout: Duration,
+        policy: RetryPolicy,
+    ) -> Result<reqwest::Response, HubError> {
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .http
+                .post(url)
+                .timeout(timeout)
+                .json(&body())
+                .send()
+                .await;
+            let last_attempt = attempt + 1 >= policy.attempts;
+
+            let failure = match result {
+                Ok(response) if !is_transient(response.status()) || last_attempt => {
+                    return Ok(response);
+                }
+                Ok(response) => format!("HTTP {}", response.status()),
+                Err(err) if last_attempt => {
+                    return Err(HubError::Network {
+                        url: url.to_string(),
+                        detail: err.to_string(),
+                    });
+                }
+                Err(err) => err.to_string(),
+            };
+
+            let delay = policy.delay(attempt);
+            println!(
+                "[Hub] Request to {url} failed (attempt {}): {failure}. Retrying in {} ms.",
+                attempt + 1,
+                delay.as_millis()
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
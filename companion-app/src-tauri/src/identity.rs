@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey, SECRET_KEY_LENGTH};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::secrets;
+
+const IDENTITY_FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdentityFile {
+    version: u32,
+    secret_key: String,
+    public_key: String,
+}
+
+/// Hub operations an agent signs. The Hub rebuilds the same message to verify them.
+#[derive(Clone, Copy)]
+pub(crate) enum SignedAction {
+    Register,
+    Unregister,
+    Heartbeat,
+    Release,
+}
+
+impl SignedAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            SignedAction::Register => "register",
+            SignedAction::Unregister => "unregister",
+            SignedAction::Heartbeat => "heartbeat",
+            SignedAction::Release => "release",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RequestSignature {
+    public_key: String,
+    timestamp_ms: u64,
+    signature: String,
+}
+
+/// Long-lived Ed25519 keypair that proves which companion owns a screen name.
+pub(crate) struct AgentIdentity {
+    signing_key: SigningKey,
+}
+
+impl AgentIdentity {
+    pub(crate) fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    /// Reads the keypair from `path`, creating and persisting a new one on first run.
+    pub(crate) fn load_or_create(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents).map_err(|err| {
+                format!(
+                    "Agent identity at \"{}\" is unreadable ({err}). Reset the identity to create a new one.",
+                    path.display()
+                )
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                let identity = Self::generate();
+                identity.save(path)?;
+                println!(
+                    "[Identity] Created agent identity {}.",
+                    identity.fingerprint()
+                );
+                Ok(identity)
+            }
+            Err(err) => Err(format!(
+                "Failed to read agent identity from \"{}\": {err}",
+                path.display()
+            )),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let file: IdentityFile = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+        if file.version != IDENTITY_FILE_VERSION {
+            return Err(format!("unsupported version {}", file.version));
+        }
+        let bytes: [u8; SECRET_KEY_LENGTH] = BASE64_STANDARD
+            .decode(file.secret_key.trim())
+            .map_err(|err| err.to_string())?
+            .try_into()
+            .map_err(|_| "secret key has the wrong length".to_string())?;
+        let identity = Self {
+            signing_key: SigningKey::from_bytes(&bytes),
+        };
+        if identity.public_key() != file.public_key {
+            return Err("public key does not match the secret key".to_string());
+        }
+        Ok(identity)
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> Result<(), String> {
+        let file = IdentityFile {
+            version: IDENTITY_FILE_VERSION,
+            secret_key: BASE64_STANDARD.encode(self.signing_key.to_bytes()),
+            public_key: self.public_key(),
+        };
+        let contents = serde_json::to_string_pretty(&file)
+            .map_err(|err| format!("Failed to serialize agent identity: {err}"))?;
+        secrets::write_private(path, contents.as_bytes())
+            .map_err(|err| format!("Failed to save agent identity: {err}"))
+    }
+
+    pub(crate) fn public_key(&self) -> String {
+        BASE64_STANDARD.encode(self.signing_key.verifying_key().to_bytes())
+    }
+
+    /// Short, human-comparable form of the public key for logs and the UI.
+    pub(crate) fn fingerprint(&self) -> String {
+        let key = self.public_key();
+        format!("ed25519:{}", &key[..12])
+    }
+
+    /// Signs `action` for `screen_name` and `tunnel_url` at the current time.
+    pub(crate) fn sign(
+        &self,
+        action: SignedAction,
+        screen_name: &str,
+        tunnel_url: &str,
+    ) -> RequestSignature {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        let message = format!(
+            "slowly-unhinged:{}\n{screen_name}\n{tunnel_url}\n{timestamp_ms}",
+            action.as_str()
+        );
+        let signature = self.signing_key.sign(message.as_bytes());
+        RequestSignature {
+            public_key: self.public_key(),
+            timestamp_ms,
+            signature: BASE64_STANDARD.encode(signature.to_bytes()),
+        }
+    }
+}
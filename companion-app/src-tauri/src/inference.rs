@@ -0,0 +1,812 @@
+//! Typed client for the inference backends: Docker Model Runner, llama.cpp's server, Ollama,
+//! and any OpenAI-compatible endpoint. They all serve OpenAI-style chat completions; how
+//! models are listed and pulled differs per backend and lives in the submodules. Request and
+//! response types keep the wire format's snake_case field names.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::settings::{InferenceBackend, InferenceConfig};
+
+mod dmr;
+mod ollama;
+mod openai;
+
+const WARMUP_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum InferenceError {
+    /// The settings do not describe a usable backend.
+    Config { message: String },
+    /// The backend did not answer.
+    Unreachable { message: String },
+    /// The backend answered with an error status.
+    Http { status: u16, message: String },
+    /// The response did not have the expected shape.
+    InvalidResponse { message: String },
+    /// The backend cannot do what was asked, such as pulling a model.
+    Unsupported { message: String },
+}
+
+impl fmt::Display for InferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config { message }
+            | Self::InvalidResponse { message }
+            | Self::Unsupported { message } => f.write_str(message),
+            Self::Unreachable { message } => {
+                write!(f, "Could not reach the inference backend: {message}")
+            }
+            Self::Http { status, message } => {
+                write!(f, "The inference backend returned HTTP {status}: {message}")
+            }
+        }
+    }
+}
+
+impl From<String> for InferenceError {
+    fn from(message: String) -> Self {
+        Self::Config { message }
+    }
+}
+
+impl From<InferenceError> for String {
+    fn from(err: InferenceError) -> Self {
+        err.to_string()
+    }
+}
+
+impl InferenceError {
+    fn unreachable(err: reqwest::Error) -> Self {
+        Self::Unreachable {
+            message: err.to_string(),
+        }
+    }
+
+    fn invalid(what: &str, err: impl fmt::Display) -> Self {
+        Self::InvalidResponse {
+            message: format!("Failed to parse the {what}: {err}"),
+        }
+    }
+}
+
+/// A model the backend can serve, in the shape of Docker Model Runner's `GET /models` entries.
+/// Other backends' listings are converted to it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Model {
+    #[serde(default)]
+    pub(crate) id: Option<String>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) created: Option<u64>,
+    #[serde(default)]
+    pub(crate) config: Option<ModelConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ModelConfig {
+    #[serde(default)]
+    pub(crate) format: Option<String>,
+    #[serde(default)]
+    pub(crate) quantization: Option<String>,
+    #[serde(default)]
+    pub(crate) parameters: Option<String>,
+    #[serde(default)]
+    pub(crate) architecture: Option<String>,
+    #[serde(default)]
+    pub(crate) size: Option<String>,
+}
+
+/// Whether any model in `models` is tagged `model_id`.
+pub(crate) fn contains_model(models: &[Model], model_id: &str) -> bool {
+    models
+        .iter()
+        .any(|model| model.tags.iter().any(|tag| tag == model_id))
+}
+
+/// Progress of a model pull, in the shape of the newline-delimited JSON that Docker Model
+/// Runner streams from `POST /models/create`. Ollama's progress is translated into it.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum PullMessage {
+    Progress {
+        #[serde(default)]
+        message: String,
+        /// Bytes to download across all layers.
+        #[serde(default)]
+        total: u64,
+        /// Bytes downloaded so far across all layers.
+        #[serde(default)]
+        pulled: u64,
+    },
+    Success {
+        #[serde(default)]
+        message: String,
+    },
+    Error {
+        #[serde(default)]
+        message: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Turns one line of a backend's pull output into a `PullMessage`.
+enum PullDecoder {
+    Dmr,
+    Ollama(ollama::PullProgress),
+}
+
+impl PullDecoder {
+    fn decode(&mut self, line: &str) -> Result<PullMessage, InferenceError> {
+        match self {
+            Self::Dmr => serde_json::from_str(line)
+                .map_err(|err| InferenceError::invalid("model pull progress", err)),
+            Self::Ollama(progress) => progress.decode(line),
+        }
+    }
+}
+
+/// A model download in progress. Dropping it closes the connection, which cancels the
+/// download.
+pub(crate) struct ModelPull {
+    response: reqwest::Response,
+    decoder: PullDecoder,
+    lines: LineBuffer,
+    queued: VecDeque<PullMessage>,
+}
+
+impl ModelPull {
+    fn new(response: reqwest::Response, decoder: PullDecoder) -> Self {
+        Self {
+            response,
+            decoder,
+            lines: LineBuffer::default(),
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// The next progress message, or `None` once the backend closes the stream. Older Docker
+    /// Model Runner releases answer without a body, so the stream may end before `Success`.
+    pub(crate) async fn next(&mut self) -> Result<Option<PullMessage>, InferenceError> {
+        loop {
+            if let Some(message) = self.queued.pop_front() {
+                return Ok(Some(message));
+            }
+            let lines = match self
+                .response
+                .chunk()
+                .await
+                .map_err(InferenceError::unreachable)?
+            {
+                Some(bytes) => self.lines.push(&bytes),
+                None => match self.lines.finish() {
+                    Some(line) => vec![line],
+                    None => return Ok(None),
+                },
+            };
+            for line in lines {
+                let message = self.decoder.decode(&line)?;
+                self.queued.push_back(message);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub(crate) enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ContentPart {
+    Text { text: String },
+    InputAudio { input_audio: InputAudio },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct InputAudio {
+    /// Base64-encoded audio.
+    pub(crate) data: String,
+    /// Such as `wav` or `mp3`.
+    pub(crate) format: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ImageUrl {
+    pub(crate) url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ChatMessage {
+    pub(crate) role: ChatRole,
+    pub(crate) content: MessageContent,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ChatCompletionRequest {
+    pub(crate) model: String,
+    pub(crate) messages: Vec<ChatMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_tokens: Option<u32>,
+}
+
+/// What is actually sent, with the streaming options the client controls.
+#[derive(Serialize)]
+struct ChatCompletionBody<'a> {
+    #[serde(flatten)]
+    request: &'a ChatCompletionRequest,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Usage {
+    #[serde(default)]
+    pub(crate) prompt_tokens: u64,
+    #[serde(default)]
+    pub(crate) completion_tokens: u64,
+    #[serde(default)]
+    pub(crate) total_tokens: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ResponseMessage {
+    pub(crate) role: ChatRole,
+    #[serde(default)]
+    pub(crate) content: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Choice {
+    pub(crate) index: u32,
+    pub(crate) message: ResponseMessage,
+    #[serde(default)]
+    pub(crate) finish_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ChatCompletion {
+    #[serde(default)]
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) model: String,
+    pub(crate) choices: Vec<Choice>,
+    #[serde(default)]
+    pub(crate) usage: Option<Usage>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct Delta {
+    #[serde(default)]
+    pub(crate) role: Option<ChatRole>,
+    #[serde(default)]
+    pub(crate) content: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ChunkChoice {
+    pub(crate) index: u32,
+    #[serde(default)]
+    pub(crate) delta: Delta,
+    #[serde(default)]
+    pub(crate) finish_reason: Option<String>,
+}
+
+/// One server-sent event of a streaming chat completion.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ChatCompletionChunk {
+    #[serde(default)]
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) model: String,
+    #[serde(default)]
+    pub(crate) choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    pub(crate) usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorDetail {
+    Object { message: String },
+    Text(String),
+}
+
+impl ErrorDetail {
+    fn into_message(self) -> String {
+        match self {
+            Self::Object { message } | Self::Text(message) => message,
+        }
+    }
+}
+
+/// Token counts per model since the app started.
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageLedger {
+    models: BTreeMap<String, ModelUsage>,
+}
+
+#[derive(Serialize, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ModelUsage {
+    requests: u64,
+    /// Requests whose response did not report usage.
+    unreported: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    total_tokens: u64,
+}
+
+impl UsageLedger {
+    pub(crate) fn record(&mut self, model: &str, usage: Option<Usage>) {
+        let entry = self.models.entry(model.to_string()).or_default();
+        entry.requests += 1;
+        match usage {
+            Some(usage) => {
+                entry.prompt_tokens += usage.prompt_tokens;
+                entry.completion_tokens += usage.completion_tokens;
+                entry.total_tokens += usage.total_tokens;
+            }
+            None => entry.unreported += 1,
+        }
+    }
+}
+
+pub(crate) struct InferenceClient {
+    http: reqwest::Client,
+    backend: InferenceBackend,
+    base_url: String,
+    api_key: Option<String>,
+    warmup_attempts: usize,
+}
+
+impl InferenceClient {
+    pub(crate) fn new(config: &InferenceConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            backend: config.backend,
+            base_url: config.base_url.clone(),
+            api_key: config.api_key.clone(),
+            warmup_attempts: config.warmup_attempts,
+        }
+    }
+
+    pub(crate) fn backend(&self) -> InferenceBackend {
+        self.backend
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Whether the backend downloads models on request.
+    pub(crate) fn can_pull(&self) -> bool {
+        matches!(
+            self.backend,
+            InferenceBackend::Dmr | InferenceBackend::Ollama
+        )
+    }
+
+    /// How to make `model` available, for error messages.
+    pub(crate) fn pull_hint(&self, model: &str) -> String {
+        match self.backend {
+            InferenceBackend::Dmr => format!("Pull it with `docker model pull {model}` first."),
+            InferenceBackend::Ollama => format!("Pull it with `ollama pull {model}` first."),
+            InferenceBackend::LlamaCpp => "Restart llama-server with that model.".to_string(),
+            InferenceBackend::OpenAiCompatible => {
+                "Use one of the models the endpoint lists.".to_string()
+            }
+        }
+    }
+
+    pub(crate) async fn list_models(&self) -> Result<Vec<Model>, InferenceError> {
+        match self.backend {
+            InferenceBackend::Dmr => dmr::list_models(self).await,
+            InferenceBackend::LlamaCpp => openai::list_models(self, "/v1/models").await,
+            InferenceBackend::Ollama => ollama::list_models(self).await,
+            InferenceBackend::OpenAiCompatible => openai::list_models(self, "/models").await,
+        }
+    }
+
+    /// Probes the model list until the backend answers.
+    pub(crate) async fn wait_until_ready(&self) -> Result<(), InferenceError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.list_models().await {
+                Ok(_) => return Ok(()),
+                Err(err) if attempt < self.warmup_attempts => {
+                    println!("[Inference] Model list probe failed (attempt {attempt}): {err}");
+                    sleep(WARMUP_DELAY).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Asks the backend to pull `model` and returns its progress messages.
+    pub(crate) async fn pull_model(&self, model: &str) -> Result<ModelPull, InferenceError> {
+        match self.backend {
+            InferenceBackend::Dmr => dmr::pull_model(self, model).await,
+            InferenceBackend::Ollama => ollama::pull_model(self, model).await,
+            InferenceBackend::LlamaCpp | InferenceBackend::OpenAiCompatible => {
+                Err(InferenceError::Unsupported {
+                    message: format!(
+                        "{} cannot download models. {}",
+                        self.backend.name(),
+                        self.pull_hint(model)
+                    ),
+                })
+            }
+        }
+    }
+
+    pub(crate) async fn chat_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletion, InferenceError> {
+        let body = ChatCompletionBody {
+            request,
+            stream: false,
+            stream_options: None,
+        };
+        self.post_chat(&body)
+            .await?
+            .json()
+            .await
+            .map_err(|err| InferenceError::invalid("chat completion", err))
+    }
+
+    /// Streams a chat completion, calling `on_chunk` for every event, and returns the
+    /// assembled completion.
+    pub(crate) async fn chat_completion_stream(
+        &self,
+        request: &ChatCompletionRequest,
+        mut on_chunk: impl FnMut(&ChatCompletionChunk),
+    ) -> Result<ChatCompletion, InferenceError> {
+        let body = ChatCompletionBody {
+            request,
+            stream: true,
+            stream_options: Some(StreamOptions {
+                include_usage: true,
+            }),
+        };
+        let mut response = self.post_chat(&body).await?;
+
+        let mut events = SseBuffer::default();
+        let mut completion = CompletionBuilder::new(&request.model);
+        loop {
+            let (data, ended) = match response
+                .chunk()
+                .await
+                .map_err(InferenceError::unreachable)?
+            {
+                Some(bytes) => (events.push(&bytes), false),
+                // Some servers close the stream without `[DONE]`, and the last event, often
+                // the usage, may lack its closing blank line.
+                None => (events.finish().into_iter().collect(), true),
+            };
+            for data in data {
+                if data == "[DONE]" {
+                    return Ok(completion.finish());
+                }
+                let chunk = parse_chunk(&data)?;
+                on_chunk(&chunk);
+                completion.add(chunk);
+            }
+            if ended {
+                return Ok(completion.finish());
+            }
+        }
+    }
+
+    async fn post_chat(
+        &self,
+        body: &ChatCompletionBody<'_>,
+    ) -> Result<reqwest::Response, InferenceError> {
+        let path = match self.backend {
+            InferenceBackend::Dmr => dmr::CHAT_COMPLETIONS_PATH,
+            InferenceBackend::LlamaCpp | InferenceBackend::Ollama => "/v1/chat/completions",
+            InferenceBackend::OpenAiCompatible => "/chat/completions",
+        };
+        let response = self
+            .request(reqwest::Method::POST, path)
+            .json(body)
+            .send()
+            .await
+            .map_err(InferenceError::unreachable)?;
+        checked(response).await
+    }
+
+    /// A request to `path` under the base URL, authenticated when there is an API key.
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{path}", self.base_url));
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+}
+
+/// Turns an error status into `InferenceError::Http`, using the OpenAI error message when there is one.
+async fn checked(response: reqwest::Response) -> Result<reqwest::Response, InferenceError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "<unable to read response body>".to_string());
+    let message = serde_json::from_str::<ErrorBody>(&body)
+        .map(|parsed| parsed.error.into_message())
+        .unwrap_or(body);
+    Err(InferenceError::Http {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+fn parse_chunk(data: &str) -> Result<ChatCompletionChunk, InferenceError> {
+    if let Ok(error) = serde_json::from_str::<ErrorBody>(data) {
+        return Err(InferenceError::InvalidResponse {
+            message: format!(
+                "The completion stream failed: {}",
+                error.error.into_message()
+            ),
+        });
+    }
+    serde_json::from_str(data)
+        .map_err(|err| InferenceError::invalid("completion stream event", err))
+}
+
+/// Splits a server-sent event stream into the `data` of each event.
+#[derive(Default)]
+struct SseBuffer {
+    pending: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseBuffer {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+            // Comments, `event:`, `id:`, and `retry:` lines carry nothing we use.
+        }
+        events
+    }
+
+    /// The trailing event when the stream ended before the blank line that dispatches it.
+    fn finish(&mut self) -> Option<String> {
+        self.push(b"\n\n").pop()
+    }
+}
+
+/// Splits a byte stream into non-empty lines.
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        lines
+    }
+
+    /// The trailing line when the stream did not end with a newline.
+    fn finish(&mut self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+        self.pending.clear();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// Assembles streamed chunks into the completion a non-streaming request would return.
+struct CompletionBuilder {
+    id: String,
+    model: String,
+    choices: BTreeMap<u32, Choice>,
+    usage: Option<Usage>,
+}
+
+impl CompletionBuilder {
+    fn new(model: &str) -> Self {
+        Self {
+            id: String::new(),
+            model: model.to_string(),
+            choices: BTreeMap::new(),
+            usage: None,
+        }
+    }
+
+    fn add(&mut self, chunk: ChatCompletionChunk) {
+        if self.id.is_empty() {
+            self.id = chunk.id;
+        }
+        if !chunk.model.is_empty() {
+            self.model = chunk.model;
+        }
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+        for delta in chunk.choices {
+            let choice = self.choices.entry(delta.index).or_insert_with(|| Choice {
+                index: delta.index,
+                message: ResponseMessage {
+                    role: ChatRole::Assistant,
+                    content: None,
+                },
+                finish_reason: None,
+            });
+            if let Some(role) = delta.delta.role {
+                choice.message.role = role;
+            }
+            if let Some(content) = delta.delta.content {
+                choice
+                    .message
+                    .content
+                    .get_or_insert_with(String::new)
+                    .push_str(&content);
+            }
+            if delta.finish_reason.is_some() {
+                choice.finish_reason = delta.finish_reason;
+            }
+        }
+    }
+
+    fn finish(self) -> ChatCompletion {
+        ChatCompletion {
+            id: self.id,
+            model: self.model,
+            choices: self.choices.into_values().collect(),
+            usage: self.usage,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineBuffer, SseBuffer};
+
+    /// Events with multi-byte characters, a multi-line event, fields we ignore, and `[DONE]`.
+    const STREAM: &str = "data: {\"content\":\"h\u{e9}llo \u{1f986}\"}\n\n\
+                          : keep-alive\n\
+                          event: message\nid: 7\ndata: first\ndata: second\n\n\
+                          data:no-space\r\n\r\n\
+                          data: [DONE]\n\n";
+
+    fn expected_events() -> Vec<String> {
+        vec![
+            "{\"content\":\"h\u{e9}llo \u{1f986}\"}".to_string(),
+            "first\nsecond".to_string(),
+            "no-space".to_string(),
+            "[DONE]".to_string(),
+        ]
+    }
+
+    fn sse_events(chunks: &[&[u8]]) -> Vec<String> {
+        let mut buffer = SseBuffer::default();
+        let mut events: Vec<String> = chunks.iter().flat_map(|chunk| buffer.push(chunk)).collect();
+        events.extend(buffer.finish());
+        events
+    }
+
+    #[test]
+    fn sse_events_survive_a_split_at_any_byte() {
+        let bytes = STREAM.as_bytes();
+        for split in 0..=bytes.len() {
+            let (head, tail) = bytes.split_at(split);
+            assert_eq!(
+                sse_events(&[head, tail]),
+                expected_events(),
+                "split at {split}"
+            );
+        }
+    }
+
+    #[test]
+    fn sse_events_survive_one_byte_chunks() {
+        let chunks: Vec<&[u8]> = STREAM.as_bytes().chunks(1).collect();
+        assert_eq!(sse_events(&chunks), expected_events());
+    }
+
+    #[test]
+    fn sse_finish_delivers_an_event_without_its_blank_line() {
+        let usage = "{\"usage\":{\"total_tokens\":3}}";
+        for ending in ["", "\n", "\r\n"] {
+            let stream = format!("data: first\n\ndata: {usage}{ending}");
+            assert_eq!(
+                sse_events(&[stream.as_bytes()]),
+                vec!["first".to_string(), usage.to_string()],
+                "ending {ending:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn sse_finish_is_empty_after_a_complete_stream() {
+        let mut buffer = SseBuffer::default();
+        assert_eq!(buffer.push(b"data: [DONE]\n\n"), vec!["[DONE]".to_string()]);
+        assert_eq!(buffer.finish(), None);
+        assert_eq!(buffer.push(b": comment\n"), Vec::<String>::new());
+        assert_eq!(buffer.finish(), None);
+    }
+
+    #[test]
+    fn lines_survive_a_split_at_any_byte() {
+        let stream = "{\"status\":\"pulling \u{1f986}\"}\r\n\n  \n{\"status\":\"success\"}";
+        let expected = vec![
+            "{\"status\":\"pulling \u{1f986}\"}".to_string(),
+            "{\"status\":\"success\"}".to_string(),
+        ];
+        let bytes = stream.as_bytes();
+        for split in 0..=bytes.len() {
+            let (head, tail) = bytes.split_at(split);
+            let mut buffer = LineBuffer::default();
+            let mut lines = buffer.push(head);
+            lines.extend(buffer.push(tail));
+            lines.extend(buffer.finish());
+            assert_eq!(lines, expected, "split at {split}");
+        }
+    }
+}
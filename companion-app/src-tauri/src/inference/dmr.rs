@@ -0,0 +1,39 @@
+//! Docker Model Runner's native model endpoints.
+
+use serde::Serialize;
+
+use super::{checked, InferenceClient, InferenceError, Model, ModelPull, PullDecoder};
+
+pub(super) const CHAT_COMPLETIONS_PATH: &str = "/engines/llama.cpp/v1/chat/completions";
+
+/// The body of `POST /models/create`.
+#[derive(Serialize, Debug)]
+struct CreateModelRequest<'a> {
+    from: &'a str,
+}
+
+pub(super) async fn list_models(client: &InferenceClient) -> Result<Vec<Model>, InferenceError> {
+    let response = client
+        .request(reqwest::Method::GET, "/models")
+        .send()
+        .await
+        .map_err(InferenceError::unreachable)?;
+    checked(response)
+        .await?
+        .json()
+        .await
+        .map_err(|err| InferenceError::invalid("model list", err))
+}
+
+pub(super) async fn pull_model(
+    client: &InferenceClient,
+    model: &str,
+) -> Result<ModelPull, InferenceError> {
+    let response = client
+        .request(reqwest::Method::POST, "/models/create")
+        .json(&CreateModelRequest { from: model })
+        .send()
+        .await
+        .map_err(InferenceError::unreachable)?;
+    Ok(ModelPull::new(checked(response).await?, PullDecoder::Dmr))
+}
@@ -0,0 +1,148 @@
+//! Ollama's native model endpoints. Its pull progress is reported per layer and is summed
+//! into Docker Model Runner's shape.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    checked, InferenceClient, InferenceError, Model, ModelConfig, ModelPull, PullDecoder,
+    PullMessage,
+};
+
+#[derive(Deserialize, Debug)]
+struct TagList {
+    #[serde(default)]
+    models: Vec<TagEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TagEntry {
+    name: String,
+    #[serde(default)]
+    details: Option<TagDetails>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TagDetails {
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    family: Option<String>,
+    #[serde(default)]
+    parameter_size: Option<String>,
+    #[serde(default)]
+    quantization_level: Option<String>,
+}
+
+/// The body of `POST /api/pull`.
+#[derive(Serialize, Debug)]
+struct PullRequest<'a> {
+    model: &'a str,
+    stream: bool,
+}
+
+/// A line of the newline-delimited JSON that `POST /api/pull` streams.
+#[derive(Deserialize, Debug)]
+struct PullLine {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    digest: Option<String>,
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    completed: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+pub(super) async fn list_models(client: &InferenceClient) -> Result<Vec<Model>, InferenceError> {
+    let response = client
+        .request(reqwest::Method::GET, "/api/tags")
+        .send()
+        .await
+        .map_err(InferenceError::unreachable)?;
+    let list: TagList = checked(response)
+        .await?
+        .json()
+        .await
+        .map_err(|err| InferenceError::invalid("model list", err))?;
+    Ok(list.models.into_iter().map(into_model).collect())
+}
+
+/// Ollama names models `name:tag` and treats a bare name as `name:latest`, so both spellings
+/// are listed as tags.
+fn into_model(entry: TagEntry) -> Model {
+    let mut tags = vec![entry.name.clone()];
+    if let Some(bare) = entry.name.strip_suffix(":latest") {
+        tags.push(bare.to_string());
+    }
+    Model {
+        id: Some(entry.name),
+        tags,
+        created: None,
+        config: entry.details.map(|details| ModelConfig {
+            format: details.format,
+            quantization: details.quantization_level,
+            parameters: details.parameter_size,
+            architecture: details.family,
+            size: None,
+        }),
+    }
+}
+
+pub(super) async fn pull_model(
+    client: &InferenceClient,
+    model: &str,
+) -> Result<ModelPull, InferenceError> {
+    let response = client
+        .request(reqwest::Method::POST, "/api/pull")
+        .json(&PullRequest {
+            model,
+            stream: true,
+        })
+        .send()
+        .await
+        .map_err(InferenceError::unreachable)?;
+    Ok(ModelPull::new(
+        checked(response).await?,
+        PullDecoder::Ollama(PullProgress::default()),
+    ))
+}
+
+/// Bytes completed and expected per layer digest seen so far.
+#[derive(Default)]
+pub(super) struct PullProgress {
+    layers: HashMap<String, (u64, u64)>,
+}
+
+impl PullProgress {
+    pub(super) fn decode(&mut self, line: &str) -> Result<PullMessage, InferenceError> {
+        let line: PullLine = serde_json::from_str(line)
+            .map_err(|err| InferenceError::invalid("model pull progress", err))?;
+        if let Some(message) = line.error {
+            return Ok(PullMessage::Error { message });
+        }
+        if line.status == "success" {
+            return Ok(PullMessage::Success {
+                message: line.status,
+            });
+        }
+        if let (Some(digest), Some(total)) = (line.digest, line.total) {
+            self.layers
+                .insert(digest, (line.completed.unwrap_or(0), total));
+        }
+        let (pulled, total) = self
+            .layers
+            .values()
+            .fold((0, 0), |(pulled, total), &(done, size)| {
+                (pulled + done, total + size)
+            });
+        Ok(PullMessage::Progress {
+            message: line.status,
+            total,
+            pulled,
+        })
+    }
+}
@@ -0,0 +1,47 @@
+//! The OpenAI-style `GET /models` listing, served by llama.cpp's server and by any
+//! OpenAI-compatible endpoint. Neither can pull models.
+
+use serde::Deserialize;
+
+use super::{checked, InferenceClient, InferenceError, Model};
+
+#[derive(Deserialize, Debug)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    created: Option<u64>,
+}
+
+/// Lists the models at `path`, which is `/v1/models` on llama.cpp and `/models` under an
+/// OpenAI-compatible base URL that already ends in `/v1`.
+pub(super) async fn list_models(
+    client: &InferenceClient,
+    path: &str,
+) -> Result<Vec<Model>, InferenceError> {
+    let response = client
+        .request(reqwest::Method::GET, path)
+        .send()
+        .await
+        .map_err(InferenceError::unreachable)?;
+    let list: ModelList = checked(response)
+        .await?
+        .json()
+        .await
+        .map_err(|err| InferenceError::invalid("model list", err))?;
+    Ok(list
+        .data
+        .into_iter()
+        .map(|entry| Model {
+            tags: vec![entry.id.clone()],
+            id: Some(entry.id),
+            created: entry.created,
+            config: None,
+        })
+        .collect())
+}
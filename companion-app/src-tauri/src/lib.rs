@@ -1,16 +1,28 @@
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
-use serde::{Deserialize, Serialize};
-use std::{env, fs, io::ErrorKind, path::PathBuf, sync::Arc, time::Duration};
-use tauri::{AppHandle, Manager, State};
+use serde::Serialize;
+use std::{collections::HashMap, env, fs, path::PathBuf, sync::Arc, time::Duration};
+use tauri::{AppHandle, Emitter, Manager, State};
 use testcontainers::{ContainerAsync, GenericImage};
 use tokio::{
-    sync::{Mutex, Notify},
+    sync::{watch, Mutex, Notify},
     time::sleep,
 };
 
 mod docker;
+mod heartbeat;
+mod hub;
+mod identity;
+mod inference;
+mod model_download;
+mod screen_name;
+mod secrets;
+mod settings;
+mod tunnel;
 mod web_server;
+mod zoom_auth;
+
+use settings::{load_settings, ModelRole, NanobananaKeySource, Settings, WheelState};
 
 // --- Tauri State Management ---
 
@@ -32,10 +44,75 @@ pub struct AppState {
     pub(crate) background: Mutex<BackgroundState>,
     pub(crate) background_notify: Notify,
     pub(crate) nanobanana_secret: Mutex<Option<String>>,
+    pub(crate) tunnel_logs: Mutex<tunnel::TunnelLogBuffer>,
+    pub(crate) instance_id: String,
+    /// Held open so that other instances leave this one's containers alone.
+    _instance_lock: Option<std::fs::File>,
+    /// Keeps the startup cleanup and the preflight's from removing the same containers.
+    container_cleanup: Mutex<()>,
+    pub(crate) tunnel_nonce: String,
+    pub(crate) tunnel_url: Mutex<Option<String>>,
+    pub(crate) heartbeat_task: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+    pub(crate) hub_status: Mutex<heartbeat::HubStatus>,
+    registration: Mutex<Option<hub::AgentRegistration>>,
+    identity: Mutex<Option<Arc<identity::AgentIdentity>>>,
+    pub(crate) zoom_sign_in: Mutex<Option<zoom_auth::PendingZoomSignIn>>,
+    pub(crate) zoom_session: Mutex<Option<zoom_auth::ZoomSession>>,
+    pub(crate) settings: settings::SettingsStore,
+    pub(crate) secrets: secrets::SecretsVault,
+    pub(crate) model_usage: Mutex<inference::UsageLedger>,
+    model_downloads: Mutex<ModelDownloads>,
+}
+
+/// Model downloads in flight, so that they can be cancelled.
+#[derive(Default)]
+struct ModelDownloads {
+    active: HashMap<String, ModelDownload>,
+    /// Set when every download is cancelled, so that no fallbacks are pulled either.
+    stopped: bool,
+}
+
+struct ModelDownload {
+    cancel: watch::Sender<bool>,
+    /// Set when the download finishes, for roles waiting on the same model.
+    result: watch::Receiver<Option<Result<(), String>>>,
+}
+
+impl ModelDownloads {
+    /// Removes `model`'s entry if it still belongs to the download behind `result`.
+    fn forget(&mut self, model: &str, result: &watch::Receiver<Option<Result<(), String>>>) {
+        if self
+            .active
+            .get(model)
+            .is_some_and(|download| download.result.same_channel(result))
+        {
+            self.active.remove(model);
+        }
+    }
+}
+
+/// Drops a download's `ModelDownloads` entry however the download ends, including when its
+/// future is dropped or panics, so later requests for the model never wait on a dead one.
+struct ActiveDownload {
+    state: Arc<AppState>,
+    model: String,
+    result: watch::Receiver<Option<Result<(), String>>>,
+}
+
+impl Drop for ActiveDownload {
+    fn drop(&mut self) {
+        let state = self.state.clone();
+        let model = std::mem::take(&mut self.model);
+        let result = self.result.clone();
+        tauri::async_runtime::spawn(async move {
+            state.model_downloads.lock().await.forget(&model, &result);
+        });
+    }
 }
 
 impl AppState {
     fn new() -> Self {
+        let instance_id = docker::new_instance_id();
         Self {
             cloudflared_container: Mutex::new(None),
             background: Mutex::new(BackgroundState {
@@ -44,155 +121,308 @@ impl AppState {
             }),
             background_notify: Notify::new(),
             nanobanana_secret: Mutex::new(None),
+            tunnel_logs: Mutex::new(tunnel::TunnelLogBuffer::new()),
+            _instance_lock: docker::hold_instance_lock(&instance_id),
+            instance_id,
+            container_cleanup: Mutex::new(()),
+            tunnel_nonce: tunnel::new_nonce(),
+            tunnel_url: Mutex::new(None),
+            heartbeat_task: Mutex::new(None),
+            hub_status: Mutex::new(heartbeat::HubStatus::idle()),
+            registration: Mutex::new(None),
+            identity: Mutex::new(None),
+            zoom_sign_in: Mutex::new(None),
+            zoom_session: Mutex::new(None),
+            settings: settings::SettingsStore::default(),
+            secrets: secrets::SecretsVault::default(),
+            model_usage: Mutex::new(inference::UsageLedger::default()),
+            model_downloads: Mutex::new(ModelDownloads::default()),
         }
     }
 }
 
 // --- Testcontainers Logic ---
 
-const HUB_URL: &str = "https://slowlyunhinged-hub-54127830651.us-central1.run.app";
-const DMR_BASE_URL: &str = "http://localhost:12434";
-const DEFAULT_TRANSCRIPTION_MODEL_ID: &str = "hf.co/ggml-org/ultravox-v0_5-llama-3_1-8b-gguf";
-const DEFAULT_BACKGROUND_PROMPT_MODEL_ID: &str = "hf.co/unsloth/gemma-3n-e2b-it-gguf:q8_k_xl";
-const BACKEND_PORT: u16 = 41786;const DMR_WARMUP_ATTEMPTS: usize = 10;
-const DMR_WARMUP_DELAY_MS: u64 = 1_000;
-const DMR_MODEL_POLL_ATTEMPTS: usize = 60;
-const DMR_MODEL_POLL_DELAY_MS: u64 = 5_000;
-const NANO_BANANA_MODEL: &str = "gemini-2.5-flash-image";
-const NANO_BANANA_ENDPOINT: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const BACKEND_PORT: u16 = 41786;
 const NANO_BANANA_FALLBACK_MIME: &str = "image/png";
-const NANO_BANANA_ASPECT_RATIO: &str = "16:9";
+const IMAGE_PULL_PROGRESS_EVENT: &str = "image-pull-progress";
+const CHAT_DELTA_EVENT: &str = "chat-delta";
+const MODEL_DOWNLOAD_PROGRESS_EVENT: &str = "model-download-progress";
+const MODEL_SELECTION_EVENT: &str = "model-selection";
+const COMPANION_LOG_EVENT: &str = "companion-log";
 
-#[derive(Deserialize)]
-struct DmrModelEntry {
-    tags: Option<Vec<String>>,
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum LogLevel {
+    Info,
+    Error,
 }
 
-async fn list_dmr_models(client: &reqwest::Client) -> Result<Vec<DmrModelEntry>, String> {
-    let url = format!("{DMR_BASE_URL}/models");
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|err| format!("Failed to query Docker Model Runner: {err}"))?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response
-            .text()
-            .await
-            .unwrap_or_else(|_| "<unable to read response body>".to_string());
-        return Err(format!(
-            "DMR model list request failed: HTTP {status} - {body}"
-        ));
-    }
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ChatDelta<'a> {
+    stream_id: &'a str,
+    index: u32,
+    content: &'a str,
+}
 
-    response
-        .json::<Vec<DmrModelEntry>>()
-        .await
-        .map_err(|err| format!("Failed to parse DMR model list: {err}"))
+/// The model picked for one role from the profile's candidates.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RoleSelection {
+    role: ModelRole,
+    /// `None` when no candidate could be made available.
+    model: Option<String>,
+    candidates: Vec<String>,
+    /// Whether the model was downloaded during this check.
+    downloaded: bool,
+    /// Candidates passed over before `model`, with the reason.
+    skipped: Vec<SkippedModel>,
 }
 
-fn contains_model(models: &[DmrModelEntry], model_id: &str) -> bool {
-    models.iter().any(|entry| {
-        entry
-            .tags
-            .as_ref()
-            .map(|tags| tags.iter().any(|tag| tag == model_id))
-            .unwrap_or(false)
-    })
+#[derive(Serialize, Clone)]
+struct SkippedModel {
+    model: String,
+    reason: String,
 }
 
-fn missing_models(models: &[DmrModelEntry], required_models: &[String]) -> Vec<String> {
-    required_models
-        .iter()
-        .filter(|model| !contains_model(models, model))
-        .cloned()
-        .collect()
-}
-
-async fn wait_for_dmr_readiness(client: &reqwest::Client) -> Result<(), String> {
-    for attempt in 0..DMR_WARMUP_ATTEMPTS {
-        match list_dmr_models(client).await {
-            Ok(_) => return Ok(()),
-            Err(err) if attempt + 1 < DMR_WARMUP_ATTEMPTS => {
-                println!(
-                    "[DMR] Model list probe failed (attempt {}): {err}",
-                    attempt + 1
-                );
-                sleep(Duration::from_millis(DMR_WARMUP_DELAY_MS)).await;
-            }
-            Err(err) => return Err(err),
+impl RoleSelection {
+    fn describe(&self) -> String {
+        let role = match self.role {
+            ModelRole::Transcription => "Transcription",
+            ModelRole::Prompt => "Prompt",
+        };
+        let skipped = self
+            .skipped
+            .iter()
+            .map(|skipped| format!("\"{}\" ({})", skipped.model, skipped.reason))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match (&self.model, skipped.is_empty()) {
+            (Some(model), true) => format!("{role} model: \"{model}\"."),
+            (Some(model), false) => format!("{role} model: \"{model}\", after skipping {skipped}."),
+            (None, _) if self.candidates.is_empty() => format!(
+                "No {} model is set, and this inference backend has no default. Choose one in Settings.",
+                role.to_lowercase()
+            ),
+            (None, _) => format!("No {} model is available: {skipped}.", role.to_lowercase()),
         }
     }
+}
 
-    Err("Timed out waiting for Docker Model Runner to respond.".to_string())
+#[derive(Serialize, Clone)]
+struct CompanionLogEntry<'a> {
+    level: LogLevel,
+    message: &'a str,
 }
 
-async fn ensure_required_models(client: &reqwest::Client, settings: &Settings) -> Result<(), String> {
-    wait_for_dmr_readiness(client).await?;
+/// Appends a line to the companion UI's event log.
+fn emit_log(app: &AppHandle, level: LogLevel, message: &str) {
+    if let Err(err) = app.emit(COMPANION_LOG_EVENT, CompanionLogEntry { level, message }) {
+        eprintln!("[Companion] Failed to emit log entry: {err}");
+    }
+}
 
-    let mut models = list_dmr_models(client).await?;
-    let required_models = vec![
-        settings.model_transcription.clone().unwrap_or_default(),
-        settings.model_prompt.clone().unwrap_or_default(),
-    ];
-    let mut pending = missing_models(&models, &required_models);
-    if pending.is_empty() {
-        println!("[DMR] All required models are already available.");
-        return Ok(());
+/// Picks a model for every role, downloading candidates as needed. A role that no candidate
+/// can fill is reported rather than failing the check.
+async fn ensure_required_models(
+    app: &AppHandle,
+    settings: &Settings,
+) -> Result<Vec<RoleSelection>, String> {
+    let config = settings.inference()?;
+    let client = inference::InferenceClient::new(&config);
+    client.wait_until_ready().await?;
+
+    let models = client.list_models().await?;
+    let profile = settings.active_profile();
+    app.state::<Arc<AppState>>()
+        .model_downloads
+        .lock()
+        .await
+        .stopped = false;
+    let selections = futures::future::join_all(ModelRole::ALL.map(|role| {
+        select_model(
+            app,
+            &client,
+            &config,
+            &models,
+            role,
+            profile.model_candidates(role, config.backend),
+        )
+    }))
+    .await;
+
+    for selection in &selections {
+        let message = selection.describe();
+        if selection.model.is_some() {
+            println!("[Inference] {message}");
+        } else {
+            eprintln!("[Inference] {message}");
+        }
+    }
+    if let Err(err) = app.emit(MODEL_SELECTION_EVENT, &selections) {
+        eprintln!("[Inference] Failed to emit model selection: {err}");
     }
+    Ok(selections)
+}
 
-    let create_url = format!("{DMR_BASE_URL}/models/create");
+/// Returns the first candidate that is already pulled or can be pulled.
+async fn select_model(
+    app: &AppHandle,
+    client: &inference::InferenceClient,
+    config: &settings::InferenceConfig,
+    models: &[inference::Model],
+    role: ModelRole,
+    candidates: Vec<String>,
+) -> RoleSelection {
+    let mut skipped = Vec::new();
+    for (index, model) in candidates.iter().enumerate() {
+        let downloaded = !inference::contains_model(models, model);
+        if downloaded && !client.can_pull() {
+            skipped.push(SkippedModel {
+                model: model.clone(),
+                reason: format!(
+                    "Not available in {}. {}",
+                    client.backend().name(),
+                    client.pull_hint(model)
+                ),
+            });
+            continue;
+        }
+        if downloaded {
+            if let Err(reason) = pull_model(app, client, config, model).await {
+                skipped.push(SkippedModel {
+                    model: model.clone(),
+                    reason,
+                });
+                continue;
+            }
+        }
+        if index > 0 {
+            println!("[Inference] Falling back to \"{model}\" for {role:?}.");
+        }
+        return RoleSelection {
+            role,
+            model: Some(model.clone()),
+            candidates,
+            downloaded,
+            skipped,
+        };
+    }
+    RoleSelection {
+        role,
+        model: None,
+        candidates,
+        downloaded: false,
+        skipped,
+    }
+}
+
+async fn pull_model(
+    app: &AppHandle,
+    client: &inference::InferenceClient,
+    config: &settings::InferenceConfig,
+    model: &str,
+) -> Result<(), String> {
+    let state = app.state::<Arc<AppState>>().inner().clone();
+    let (cancel, finished, _active) = loop {
+        let mut downloads = state.model_downloads.lock().await;
+        if downloads.stopped {
+            return Err("downloads were cancelled".to_string());
+        }
+        // Both roles may want the same model; the second waits for the first download
+        // instead of starting another one.
+        let existing = downloads
+            .active
+            .get(model)
+            .map(|download| download.result.clone());
+        match existing {
+            Some(mut result) => {
+                drop(downloads);
+                println!("[Inference] \"{model}\" is already downloading. Waiting for it.");
+                if let Ok(result) = result.wait_for(Option::is_some).await {
+                    return result.clone().expect("waited for a result");
+                }
+                // The download ended without a result; start a fresh one.
+                state.model_downloads.lock().await.forget(model, &result);
+            }
+            None => {
+                let (cancel, cancelled) = watch::channel(false);
+                let (finished, result) = watch::channel(None);
+                let active = ActiveDownload {
+                    state: state.clone(),
+                    model: model.to_string(),
+                    result: result.clone(),
+                };
+                downloads
+                    .active
+                    .insert(model.to_string(), ModelDownload { cancel, result });
+                break (cancelled, finished, active);
+            }
+        }
+    };
     println!(
-        "[DMR] Missing models: {}. Requesting downloads via {create_url}.",
-        pending.join(", ")
+        "[Inference] Requesting \"{model}\" from {}.",
+        client.base_url()
     );
+    let result = download_model(app, client, config, model, cancel).await;
+    finished.send_replace(Some(result.clone()));
+    result
+}
 
-    for model in &pending {
-        let response = client
-            .post(&create_url)
-            .json(&serde_json::json!({ "from": model }))
-            .send()
-            .await
-            .map_err(|err| format!("Failed to request download for model \"{model}\": {err}"))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "<unable to read response body>".to_string());
-            return Err(format!(
-                "Model download request for \"{model}\" failed: HTTP {status} - {body}"
-            ));
-        }
-    }
+async fn download_model(
+    app: &AppHandle,
+    client: &inference::InferenceClient,
+    config: &settings::InferenceConfig,
+    model: &str,
+    cancel: watch::Receiver<bool>,
+) -> Result<(), String> {
+    let outcome = model_download::download(
+        client,
+        model,
+        config.pull_stall_timeout,
+        config.download_budget,
+        cancel,
+        |progress| {
+            if let Err(err) = app.emit(MODEL_DOWNLOAD_PROGRESS_EVENT, progress) {
+                eprintln!("[Inference] Failed to emit model download progress: {err}");
+            }
+        },
+    )
+    .await;
 
-    println!("[DMR] Download requests accepted. Polling for model availability…");
+    match outcome? {
+        model_download::PullOutcome::Complete => Ok(()),
+        model_download::PullOutcome::Unconfirmed => wait_for_model(client, config, model).await,
+    }
+}
 
-    for attempt in 0..DMR_MODEL_POLL_ATTEMPTS {
-        sleep(Duration::from_millis(DMR_MODEL_POLL_DELAY_MS)).await;
-        models = list_dmr_models(client).await?;
-        pending.retain(|model| !contains_model(&models, model));
-        if pending.is_empty() {
+/// Polls the model list until `model` is present, for backend releases that do not stream
+/// pull progress.
+async fn wait_for_model(
+    client: &inference::InferenceClient,
+    config: &settings::InferenceConfig,
+    model: &str,
+) -> Result<(), String> {
+    println!("[Inference] No pull progress reported for \"{model}\". Polling for it…");
+
+    for attempt in 0..config.model_poll_attempts {
+        let models = client.list_models().await?;
+        if inference::contains_model(&models, model) {
             println!(
-                "[DMR] All required models are available after {} poll attempts.",
+                "[Inference] \"{model}\" is available after {} poll attempts.",
                 attempt + 1
             );
             return Ok(());
         }
         println!(
-            "[DMR] Waiting for models to download (attempt {}): pending {}",
-            attempt + 1,
-            pending.join(", ")
+            "[Inference] Waiting for \"{model}\" to download (attempt {}).",
+            attempt + 1
         );
+        sleep(config.model_poll_delay).await;
     }
 
-    Err(format!(
-        "Timed out waiting for required models to become available: {}",
-        pending.join(", ")
-    ))
+    Err("timed out waiting for the download to finish".to_string())
 }
 
 async fn start_and_register_agent(
@@ -201,106 +431,174 @@ async fn start_and_register_agent(
     app_state: &Arc<AppState>,
 ) -> Result<String, String> {
     let http_client = reqwest::Client::new();
+    let screen_name = screen_name::ScreenName::parse(screen_name)?;
+    settings::update(app, |settings| {
+        settings.active_profile_mut().screen_name = Some(screen_name.normalized().to_string());
+        Ok::<_, String>(())
+    })?;
     let settings = load_settings(app)?;
 
-    ensure_required_models(&http_client, &settings).await?;
+    for selection in ensure_required_models(app, &settings).await? {
+        if selection.model.is_none() {
+            emit_log(
+                app,
+                LogLevel::Error,
+                &format!("[Inference] {}", selection.describe()),
+            );
+        }
+    }
 
-    let (cloudflared_container, tunnel_url) = docker::start_cloudflared(BACKEND_PORT).await?;
-    sleep(Duration::from_secs(3)).await; // allow DNS propagation so the Hub can resolve the tunnel
+    let cloudflared_container = docker::start_cloudflared(
+        &settings.cloudflared_image(),
+        BACKEND_PORT,
+        &app_state.instance_id,
+    )
+    .await?;
+    let log_lines = docker::follow_logs(&cloudflared_container);
+    let tunnel_url = tunnel::follow(app.clone(), app_state.clone(), log_lines).await?;
+    tunnel::verify_public_url(&http_client, &tunnel_url, &app_state.tunnel_nonce).await?;
 
     let mut guard = app_state.cloudflared_container.lock().await;
     *guard = Some(cloudflared_container);
     drop(guard);
-
-    let hub_api_url = format!("{}/api/register-agent", HUB_URL);
-    let sanitized_screen_name = screen_name.trim();
-    if sanitized_screen_name.is_empty() {
-        return Err("Screen name must not be empty.".to_string());
+    app_state
+        .tunnel_url
+        .lock()
+        .await
+        .replace(tunnel_url.clone());
+
+    let hub = hub::HubClient::new(
+        settings.hub_endpoints()?,
+        load_agent_identity(app, app_state).await?,
+    )?;
+    let registration = hub::AgentRegistration {
+        screen_name: screen_name.normalized().to_string(),
+        tunnel_url: tunnel_url.clone(),
+    };
+    register_with_hub(app, app_state, &hub, &registration).await?;
+    app_state
+        .registration
+        .lock()
+        .await
+        .replace(registration.clone());
+
+    let hub_base_url = hub.endpoints().base_url.clone();
+    let heartbeat = heartbeat::spawn(
+        app.clone(),
+        app_state.clone(),
+        hub,
+        settings.hub_heartbeat_interval()?,
+        registration,
+    );
+    if let Some(previous) = app_state.heartbeat_task.lock().await.replace(heartbeat) {
+        previous.abort();
     }
-    let has_local_nanobanana_key = has_local_nanobanana_key(app)?;
-    let requires_nanobanana_key = true;
-    let payload = RegisterAgentPayload {
-        screen_name: sanitized_screen_name,
-        tunnel_url: &tunnel_url,
-        requires_nanobanana_key,
-        has_local_nanobanana_key,
+
+    let verified_as = app_state
+        .zoom_session
+        .lock()
+        .await
+        .as_ref()
+        .map(|session| format!(", Zoom user: {}", session.account.user_id))
+        .unwrap_or_default();
+    Ok(format!(
+        "Agent registered with tunnel: {} (Hub: {}{})",
+        tunnel_url, hub_base_url, verified_as
+    ))
+}
+
+/// Removes the current registration from the Hub, if any. Failures are reported but
+/// never block shutdown; the Hub expires stale registrations on its own.
+async fn unregister_agent(app: &AppHandle, state: &Arc<AppState>) {
+    let Some(registration) = state.registration.lock().await.take() else {
+        return;
+    };
+
+    let outcome = match hub_client(app, state).await {
+        Ok(hub) => hub.unregister(&registration).await.map_err(String::from),
+        Err(err) => Err(err),
     };
-    let res = http_client.post(&hub_api_url).json(&payload).send().await;
 
-    match res {
-        Ok(response) if response.status().is_success() => {
-            Ok(format!("Agent registered with tunnel: {}", tunnel_url))
+    match outcome {
+        Ok(()) => {
+            let message = format!(
+                "[Hub] Unregistered \"{}\" ({}).",
+                registration.screen_name, registration.tunnel_url
+            );
+            println!("{message}");
+            emit_log(app, LogLevel::Info, &message);
+        }
+        Err(err) => {
+            let message = format!(
+                "[Hub] Failed to unregister \"{}\"; the Hub will expire it shortly: {err}",
+                registration.screen_name
+            );
+            eprintln!("{message}");
+            emit_log(app, LogLevel::Error, &message);
         }
-        Ok(response) => Err(format!(
-            "Failed to register agent: {}",
-            response.text().await.unwrap_or_default()
-        )),
-        Err(err) => Err(format!("Request to Hub failed: {}", err)),
     }
 }
 
-// --- Tauri Commands ---
-
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct RegisterAgentPayload<'a> {
-    screen_name: &'a str,
-    tunnel_url: &'a str,
-    requires_nanobanana_key: bool,
-    has_local_nanobanana_key: bool,
+async fn hub_client(app: &AppHandle, state: &Arc<AppState>) -> Result<hub::HubClient, String> {
+    let endpoints = load_settings(app)?.hub_endpoints()?;
+    hub::HubClient::new(endpoints, load_agent_identity(app, state).await?)
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct WheelState {
-    positions: Vec<usize>,
-    active_index: usize,
+/// Returns the agent's keypair, loading it from disk (or creating it) on first use.
+async fn load_agent_identity(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+) -> Result<Arc<identity::AgentIdentity>, String> {
+    let mut guard = state.identity.lock().await;
+    if let Some(identity) = guard.as_ref() {
+        return Ok(identity.clone());
+    }
+    let identity = Arc::new(identity::AgentIdentity::load_or_create(
+        &agent_identity_path(app)?,
+    )?);
+    guard.replace(identity.clone());
+    Ok(identity)
 }
 
-#[derive(Serialize, Deserialize, Default, Clone)]
-#[serde(rename_all = "camelCase")]
-struct Settings {
-    #[serde(default)]
-    wheels: Option<WheelState>,
-    #[serde(default)]
-    nanobanana_api_key: Option<String>,
-    #[serde(default)]
-    model_transcription: Option<String>,
-    #[serde(default)]
-    model_prompt: Option<String>,
+/// Registers with the Hub, bound to the signed-in Zoom account when there is one.
+async fn register_with_hub(
+    app: &AppHandle,
+    state: &AppState,
+    hub: &hub::HubClient,
+    registration: &hub::AgentRegistration,
+) -> Result<(), String> {
+    let has_local_nanobanana_key = has_local_nanobanana_key(app)?;
+    let zoom_access_token = zoom_auth::access_token(app, state).await?;
+    hub.register(
+        registration,
+        has_local_nanobanana_key,
+        zoom_access_token.as_deref(),
+    )
+    .await
+    .map_err(String::from)
 }
 
+// --- Tauri Commands ---
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct BackgroundImageResult {
     data_url: String,
 }
 
-fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let mut dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|err| format!("Unable to resolve config directory: {err}"))?;
-
-    fs::create_dir_all(&dir).map_err(|err| format!("Unable to create config directory: {err}"))?;
-
-    dir.push("settings.json");
-    Ok(dir)
-}
-
-fn legacy_wheel_state_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let mut dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|err| format!("Unable to resolve config directory: {err}"))?;
-
-    fs::create_dir_all(&dir).map_err(|err| format!("Unable to create config directory: {err}"))?;
-
-    dir.push("wheel_state.json");
-    Ok(dir)
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentStatus {
+    running: bool,
+    tunnel_url: Option<String>,
+    /// `None` while the Hub settings are invalid.
+    hub: Option<hub::HubEndpoints>,
+    hub_status: heartbeat::HubStatus,
+    identity: String,
+    zoom_account: Option<zoom_auth::ZoomAccount>,
 }
 
-fn nanobanana_key_path(app: &AppHandle) -> Result<PathBuf, String> {
+fn agent_identity_path(app: &AppHandle) -> Result<PathBuf, String> {
     let mut dir = app
         .path()
         .app_config_dir()
@@ -308,156 +606,107 @@ fn nanobanana_key_path(app: &AppHandle) -> Result<PathBuf, String> {
 
     fs::create_dir_all(&dir).map_err(|err| format!("Unable to create config directory: {err}"))?;
 
-    dir.push("nanobanana_api_key.txt");
+    dir.push("agent_identity.json");
     Ok(dir)
 }
 
-fn load_settings(app: &AppHandle) -> Result<Settings, String> {
-    let path = settings_path(app)?;
-    match fs::read_to_string(&path) {
-        Ok(contents) => {
-            let mut settings: Settings = serde_json::from_str(&contents)
-                .map_err(|err| format!("Failed to parse settings: {err}"))?;
-
-            let mut needs_save = false;
-            if settings.model_transcription.is_none() {
-                settings.model_transcription = Some(DEFAULT_TRANSCRIPTION_MODEL_ID.to_string());
-                needs_save = true;
-            }
-            if settings.model_prompt.is_none() {
-                settings.model_prompt = Some(DEFAULT_BACKGROUND_PROMPT_MODEL_ID.to_string());
-                needs_save = true;
-            }
-
-            if needs_save {
-                save_settings(app, &settings)?;
-            }
-
-            Ok(settings)
-        }
-        Err(err) if err.kind() == ErrorKind::NotFound => {
-            let mut settings = if let Some(settings) = migrate_legacy_wheel_state(app)? {
-                if let Ok(legacy_path) = legacy_wheel_state_path(app) {
-                    let _ = fs::remove_file(legacy_path);
-                }
-                settings
-            } else {
-                Settings::default()
-            };
-
-            settings.model_transcription = Some(DEFAULT_TRANSCRIPTION_MODEL_ID.to_string());
-            settings.model_prompt = Some(DEFAULT_BACKGROUND_PROMPT_MODEL_ID.to_string());
-            save_settings(app, &settings)?;
-
-            Ok(settings)
-        }
-        Err(err) => Err(format!("Failed to read settings: {err}")),
-    }
-}
-
-fn save_settings(app: &AppHandle, settings: &Settings) -> Result<(), String> {
-    let path = settings_path(app)?;
-    let contents = serde_json::to_string_pretty(settings)
-        .map_err(|err| format!("Failed to serialize settings: {err}"))?;
-    fs::write(&path, contents).map_err(|err| format!("Failed to write settings: {err}"))?;
-    Ok(())
-}
-
-fn migrate_legacy_wheel_state(app: &AppHandle) -> Result<Option<Settings>, String> {
-    let legacy_path = legacy_wheel_state_path(app)?;
-    match fs::read_to_string(&legacy_path) {
-        Ok(contents) => {
-            let wheels: WheelState = serde_json::from_str(&contents)
-                .map_err(|err| format!("Failed to parse legacy wheel state: {err}"))?;
-            Ok(Some(Settings {
-                wheels: Some(wheels),
-                ..Default::default()
-            }))
-        }
-        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
-        Err(err) => Err(format!("Failed to read legacy wheel state: {err}")),
-    }
-}
-
 fn has_local_nanobanana_key(app: &AppHandle) -> Result<bool, String> {
     let settings = load_settings(app)?;
-    if settings
-        .nanobanana_api_key
-        .as_ref()
-        .map(|value| !value.trim().is_empty())
-        .unwrap_or(false)
+    let profile = settings.active_profile();
+    let source = profile.nanobanana_key_source;
+    let allows = |candidate| source == NanobananaKeySource::Auto || source == candidate;
+    let vault = &app.state::<Arc<AppState>>().secrets;
+
+    if allows(NanobananaKeySource::Settings)
+        && vault.contains(
+            app,
+            &secrets::profile_nanobanana_key(settings.active_profile_name()),
+        )?
     {
         return Ok(true);
     }
 
-    if let Ok(value) = env::var("NANOBANANA_API_KEY") {
-        if !value.trim().is_empty() {
-            return Ok(true);
+    if allows(NanobananaKeySource::Environment) {
+        if let Ok(value) = env::var("NANOBANANA_API_KEY") {
+            if !value.trim().is_empty() {
+                return Ok(true);
+            }
         }
     }
 
-    let path = nanobanana_key_path(app)?;
-    match fs::read_to_string(&path) {
-        Ok(contents) => Ok(!contents.trim().is_empty()),
-        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
-        Err(err) => Err(format!(
-            "Failed to read nano banana API key from \"{}\": {err}",
-            path.display()
-        )),
-    }
+    Ok(allows(NanobananaKeySource::File) && vault.contains(app, secrets::SHARED_NANOBANANA_KEY)?)
 }
 
 async fn load_nanobanana_api_key(app: &AppHandle, state: &Arc<AppState>) -> Result<String, String> {
+    resolve_nanobanana_api_key(app, state)
+        .await
+        .map(|(key, _, _)| key)
+}
+
+/// The key along with where it came from, for `describe_config`.
+async fn resolve_nanobanana_api_key(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+) -> Result<(String, settings::ConfigSource, String), String> {
     let settings = load_settings(app)?;
-    if let Some(value) = settings
-        .nanobanana_api_key
-        .as_ref()
-        .map(|value| value.trim())
-        .filter(|trimmed| !trimmed.is_empty())
-    {
-        return Ok(value.to_string());
+    let profile = settings.active_profile();
+    let source = profile.nanobanana_key_source;
+    let allows = |candidate| source == NanobananaKeySource::Auto || source == candidate;
+
+    if allows(NanobananaKeySource::Settings) {
+        let entry = secrets::profile_nanobanana_key(settings.active_profile_name());
+        if let Some(value) = state.secrets.get(app, &entry)? {
+            return Ok((value, settings::ConfigSource::Vault, entry));
+        }
     }
 
-    if let Ok(value) = env::var("NANOBANANA_API_KEY") {
-        let trimmed = value.trim();
-        if !trimmed.is_empty() {
-            return Ok(trimmed.to_string());
+    if allows(NanobananaKeySource::Environment) {
+        if let Ok(value) = env::var("NANOBANANA_API_KEY") {
+            let trimmed = value.trim();
+            if !trimmed.is_empty() {
+                return Ok((
+                    trimmed.to_string(),
+                    settings::ConfigSource::Environment,
+                    "NANOBANANA_API_KEY".to_string(),
+                ));
+            }
         }
     }
 
-    if let Some(value) = {
-        let guard = state.nanobanana_secret.lock().await;
-        guard.clone()
-    } {
-        return Ok(value);
+    if allows(NanobananaKeySource::Hub) {
+        if let Some(value) = {
+            let guard = state.nanobanana_secret.lock().await;
+            guard.clone()
+        } {
+            return Ok((
+                value,
+                settings::ConfigSource::Hub,
+                "Hub default key".to_string(),
+            ));
+        }
     }
 
-    let settings_file = settings_path(app)?;
-    let path = nanobanana_key_path(app)?;
-    match fs::read_to_string(&path) {
-        Ok(contents) => {
-            let trimmed = contents.trim();
-            if trimmed.is_empty() {
-                Err(format!(
-                    "Nano banana API key file at \"{}\" is empty.",
-                    path.display()
-                ))
-            } else {
-                Ok(trimmed.to_string())
-            }
-        }
-        Err(err) if err.kind() == ErrorKind::NotFound => Err(format!(
-            "Nano banana API key not configured. \
-             Provide one by setting \"nanobananaApiKey\" in \"{}\", \
-             exporting the NANOBANANA_API_KEY environment variable, \
-             placing the key in \"{}\", \
-             or ensuring the Hub delivers a default key.",
-            settings_file.display(),
-            path.display()
+    if !allows(NanobananaKeySource::File) {
+        return Err(format!(
+            "Nano banana API key not available: profile \"{}\" only uses the {source:?} key source.",
+            settings.active_profile_name()
+        ));
+    }
+
+    match state.secrets.get(app, secrets::SHARED_NANOBANANA_KEY)? {
+        Some(value) => Ok((
+            value,
+            settings::ConfigSource::Vault,
+            secrets::SHARED_NANOBANANA_KEY.to_string(),
         )),
-        Err(err) => Err(format!(
-            "Failed to read nano banana API key from \"{}\": {err}",
-            path.display()
+        None => Err(format!(
+            "Nano banana API key not configured. \
+             Save one for profile \"{}\" in the Settings panel, \
+             export the NANOBANANA_API_KEY environment variable, \
+             place the key in \"{}\" to import it into the secrets vault at the next launch, \
+             or ensure the Hub delivers a default key.",
+            settings.active_profile_name(),
+            secrets::legacy_key_file_path(app)?.display()
         )),
     }
 }
@@ -513,19 +762,154 @@ async fn register_agent(
 }
 
 #[tauri::command]
-async fn check_docker_access() -> Result<(), String> {
-    docker::verify_cloudflared_container().await
+async fn check_docker_access(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let settings = load_settings(&app)?;
+    docker::verify_cloudflared_container(&settings.cloudflared_image(), &state.instance_id).await
 }
 
 #[tauri::command]
-async fn ensure_models_ready(app: AppHandle) -> Result<(), String> {
-    let client = reqwest::Client::new();
+async fn cleanup_orphaned_containers(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<docker::OrphanedContainer>, String> {
+    remove_orphaned_containers(&state).await
+}
+
+async fn remove_orphaned_containers(
+    state: &AppState,
+) -> Result<Vec<docker::OrphanedContainer>, String> {
+    let _cleanup = state.container_cleanup.lock().await;
+    docker::remove_orphaned_containers(&state.instance_id).await
+}
+
+#[tauri::command]
+async fn prepare_images(app: AppHandle) -> Result<(), String> {
+    let settings = load_settings(&app)?;
+    docker::prepare_image(&settings.cloudflared_image(), |progress| {
+        if let Err(err) = app.emit(IMAGE_PULL_PROGRESS_EVENT, &progress) {
+            eprintln!("[Docker] Failed to emit image pull progress: {err}");
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+async fn ensure_models_ready(app: AppHandle) -> Result<Vec<RoleSelection>, String> {
     let settings = load_settings(&app)?;
-    ensure_required_models(&client, &settings).await
+    ensure_required_models(&app, &settings).await
+}
+
+/// Cancels the download of `model`, which moves on to the next candidate for its role. Without
+/// a `model`, cancels every download and pulls no further candidates until the next check.
+/// Returns the models whose downloads were cancelled.
+#[tauri::command]
+async fn cancel_model_download(
+    state: State<'_, Arc<AppState>>,
+    model: Option<String>,
+) -> Result<Vec<String>, String> {
+    let mut downloads = state.model_downloads.lock().await;
+    if model.is_none() {
+        downloads.stopped = true;
+    }
+    let cancelled: Vec<String> = downloads
+        .active
+        .iter()
+        .filter(|(name, _)| model.as_ref().is_none_or(|model| model == *name))
+        .map(|(name, download)| {
+            download.cancel.send_replace(true);
+            name.clone()
+        })
+        .collect();
+    Ok(cancelled)
+}
+
+fn inference_client(app: &AppHandle) -> Result<inference::InferenceClient, String> {
+    Ok(inference::InferenceClient::new(
+        &load_settings(app)?.inference()?,
+    ))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InferenceBackendInfo {
+    backend: settings::InferenceBackend,
+    name: &'static str,
+    base_url: String,
+    can_pull: bool,
+}
+
+/// Which inference backend the settings select, for the preflight checklist.
+#[tauri::command]
+async fn get_inference_backend(app: AppHandle) -> Result<InferenceBackendInfo, String> {
+    let client = inference_client(&app)?;
+    Ok(InferenceBackendInfo {
+        backend: client.backend(),
+        name: client.backend().name(),
+        base_url: client.base_url().to_string(),
+        can_pull: client.can_pull(),
+    })
 }
 
 #[tauri::command]
-async fn stop_agent(state: State<'_, Arc<AppState>>) -> Result<String, String> {
+async fn list_models(app: AppHandle) -> Result<Vec<inference::Model>, inference::InferenceError> {
+    inference_client(&app)?.list_models().await
+}
+
+/// Runs a chat completion against the inference backend. With a `stream_id`, the response is
+/// streamed and every delta is emitted as a `chat-delta` event carrying that id.
+#[tauri::command]
+async fn chat_completion(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    request: inference::ChatCompletionRequest,
+    stream_id: Option<String>,
+) -> Result<inference::ChatCompletion, inference::InferenceError> {
+    let client = inference_client(&app)?;
+    let completion = match stream_id {
+        Some(stream_id) => {
+            client
+                .chat_completion_stream(&request, |chunk| {
+                    for choice in &chunk.choices {
+                        let Some(content) = &choice.delta.content else {
+                            continue;
+                        };
+                        let delta = ChatDelta {
+                            stream_id: &stream_id,
+                            index: choice.index,
+                            content,
+                        };
+                        if let Err(err) = app.emit(CHAT_DELTA_EVENT, delta) {
+                            eprintln!("[Inference] Failed to emit chat delta: {err}");
+                        }
+                    }
+                })
+                .await?
+        }
+        None => client.chat_completion(&request).await?,
+    };
+    state
+        .model_usage
+        .lock()
+        .await
+        .record(&request.model, completion.usage);
+    Ok(completion)
+}
+
+/// Tokens used per model since the app started.
+#[tauri::command]
+async fn get_model_usage(
+    state: State<'_, Arc<AppState>>,
+) -> Result<inference::UsageLedger, String> {
+    Ok(state.model_usage.lock().await.clone())
+}
+
+#[tauri::command]
+async fn stop_agent(app: AppHandle, state: State<'_, Arc<AppState>>) -> Result<String, String> {
+    heartbeat::stop(&app, state.inner()).await;
+    unregister_agent(&app, state.inner()).await;
+
     let container = {
         let mut guard = state.cloudflared_container.lock().await;
         guard.take()
@@ -540,6 +924,7 @@ async fn stop_agent(state: State<'_, Arc<AppState>>) -> Result<String, String> {
                     let mut guard = state.nanobanana_secret.lock().await;
                     guard.take();
                 }
+                state.tunnel_url.lock().await.take();
                 Ok("Agent stopped successfully.".to_string())
             }
         }
@@ -547,22 +932,437 @@ async fn stop_agent(state: State<'_, Arc<AppState>>) -> Result<String, String> {
     }
 }
 
+#[tauri::command]
+async fn get_agent_status(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<AgentStatus, String> {
+    let settings = load_settings(&app)?;
+    let running = state.cloudflared_container.lock().await.is_some();
+    let tunnel_url = state.tunnel_url.lock().await.clone();
+    let hub_status = state.hub_status.lock().await.clone();
+    let identity = load_agent_identity(&app, state.inner()).await?;
+    Ok(AgentStatus {
+        running,
+        tunnel_url,
+        hub: settings.hub_endpoints().ok(),
+        hub_status,
+        identity: identity.fingerprint(),
+        zoom_account: state
+            .zoom_session
+            .lock()
+            .await
+            .as_ref()
+            .map(|session| session.account.clone()),
+    })
+}
+
+/// Signs in with Zoom so the next registration is bound to a verified Zoom account.
+#[tauri::command]
+async fn sign_in_with_zoom(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<zoom_auth::ZoomAccount, String> {
+    let config = load_settings(&app)?.zoom_oauth()?;
+    let session = zoom_auth::sign_in(&app, state.inner(), &config).await?;
+    let account = session.account.clone();
+    state.zoom_session.lock().await.replace(session);
+    emit_log(
+        &app,
+        LogLevel::Info,
+        &format!("[Zoom] Signed in as Zoom user {}.", account.user_id),
+    );
+    Ok(account)
+}
+
+#[tauri::command]
+async fn sign_out_of_zoom(state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    state.zoom_session.lock().await.take();
+    Ok(())
+}
+
+/// Replaces the agent keypair. The old key first releases its claim on `screen_name`
+/// (or the last screen name the agent started with) so the new key can register it.
+#[tauri::command]
+async fn reset_agent_identity(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    screen_name: Option<String>,
+) -> Result<String, String> {
+    if state.cloudflared_container.lock().await.is_some() {
+        return Err("Stop the agent before resetting its identity.".to_string());
+    }
+
+    let screen_name = match screen_name.as_deref().map(str::trim) {
+        Some(raw) if !raw.is_empty() => Some(
+            screen_name::ScreenName::parse(raw)?
+                .normalized()
+                .to_string(),
+        ),
+        _ => load_settings(&app)?.active_profile().screen_name.clone(),
+    };
+    if let Some(screen_name) = screen_name {
+        let hub = hub_client(&app, state.inner()).await?;
+        hub.release_identity(&screen_name).await.map_err(|err| {
+            format!("Failed to release \"{screen_name}\" on the Hub; identity was not reset: {err}")
+        })?;
+        println!("[Identity] Released \"{screen_name}\" on the Hub.");
+    }
+
+    let identity = identity::AgentIdentity::generate();
+    identity.save(&agent_identity_path(&app)?)?;
+    let fingerprint = identity.fingerprint();
+    state.identity.lock().await.replace(Arc::new(identity));
+
+    let message = format!("[Identity] Agent identity reset; new identity is {fingerprint}.");
+    println!("{message}");
+    emit_log(&app, LogLevel::Info, &message);
+    Ok(fingerprint)
+}
+
+#[tauri::command]
+async fn get_tunnel_logs(
+    state: State<'_, Arc<AppState>>,
+    limit: Option<usize>,
+) -> Result<Vec<tunnel::TunnelLogEntry>, String> {
+    let logs = state.tunnel_logs.lock().await;
+    Ok(logs.recent(limit.unwrap_or(usize::MAX)))
+}
+
+/// Shows the user the identity a screen name will register under before the agent starts.
+#[tauri::command]
+async fn preview_screen_name(screen_name: String) -> Result<screen_name::ScreenName, String> {
+    screen_name::ScreenName::parse(&screen_name)
+}
+
 #[tauri::command]
 async fn save_wheel_state(app: AppHandle, state: WheelState) -> Result<(), String> {
-    let mut settings = load_settings(&app)?;
-    settings.wheels = Some(state);
-    save_settings(&app, &settings)
+    settings::update(&app, |settings| {
+        settings.active_profile_mut().wheels = Some(state);
+        Ok(())
+    })
 }
 
 #[tauri::command]
 async fn load_wheel_state(app: AppHandle) -> Result<Option<WheelState>, String> {
     let settings = load_settings(&app)?;
-    Ok(settings.wheels)
+    Ok(settings.active_profile().wheels.clone())
+}
+
+#[tauri::command]
+async fn list_profiles(app: AppHandle) -> Result<Vec<settings::ProfileSummary>, String> {
+    Ok(load_settings(&app)?.profile_summaries())
+}
+
+#[tauri::command]
+async fn create_profile(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    name: String,
+    copy_from: Option<String>,
+) -> Result<Vec<settings::ProfileSummary>, String> {
+    let (settings, source) = settings::update(&app, |settings| {
+        let source = copy_from
+            .as_deref()
+            .and_then(|source| settings.find_profile(source));
+        settings.create_profile(&name, copy_from.as_deref())?;
+        Ok::<_, String>((settings.clone(), source))
+    })?;
+
+    if let Some(source) = source {
+        let key = state
+            .secrets
+            .get(&app, &secrets::profile_nanobanana_key(&source))?;
+        if let Some(key) = key {
+            state.secrets.set(
+                &app,
+                &secrets::profile_nanobanana_key(name.trim()),
+                Some(&key),
+            )?;
+        }
+    }
+    Ok(settings.profile_summaries())
+}
+
+/// Makes `name` the active profile. The running agent keeps the identity it registered
+/// with, so switching requires stopping it first.
+#[tauri::command]
+async fn switch_profile(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    name: String,
+) -> Result<Vec<settings::ProfileSummary>, String> {
+    if state.cloudflared_container.lock().await.is_some() {
+        return Err("Stop the agent before switching profiles.".to_string());
+    }
+    let settings = settings::update(&app, |settings| {
+        settings.switch_profile(&name)?;
+        Ok::<_, String>(settings.clone())
+    })?;
+    emit_log(
+        &app,
+        LogLevel::Info,
+        &format!(
+            "[Settings] Switched to profile \"{}\".",
+            settings.active_profile_name()
+        ),
+    );
+    Ok(settings.profile_summaries())
+}
+
+#[tauri::command]
+async fn delete_profile(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    name: String,
+) -> Result<Vec<settings::ProfileSummary>, String> {
+    let (settings, name) = settings::update(&app, |settings| {
+        let name = settings
+            .find_profile(&name)
+            .unwrap_or_else(|| name.trim().to_string());
+        settings.delete_profile(&name)?;
+        Ok::<_, String>((settings.clone(), name))
+    })?;
+    state
+        .secrets
+        .set(&app, &secrets::profile_nanobanana_key(&name), None)?;
+    Ok(settings.profile_summaries())
+}
+
+/// Stores the active profile's Nano Banana key in the encrypted secrets vault.
+#[tauri::command]
+async fn set_nanobanana_api_key(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    api_key: String,
+) -> Result<(), String> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err("API key must not be empty.".to_string());
+    }
+    let settings = load_settings(&app)?;
+    state.secrets.set(
+        &app,
+        &secrets::profile_nanobanana_key(settings.active_profile_name()),
+        Some(api_key),
+    )?;
+    println!(
+        "[Secrets] Stored a Nano Banana key for profile \"{}\".",
+        settings.active_profile_name()
+    );
+    Ok(())
+}
+
+#[tauri::command]
+async fn clear_nanobanana_api_key(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let settings = load_settings(&app)?;
+    state.secrets.set(
+        &app,
+        &secrets::profile_nanobanana_key(settings.active_profile_name()),
+        None,
+    )
+}
+
+/// Checks `api_key` (or the key the active profile would use) against the Gemini API
+/// without generating an image.
+#[tauri::command]
+async fn test_nanobanana_api_key(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    api_key: Option<String>,
+) -> Result<String, String> {
+    let api_key = match api_key.as_deref().map(str::trim) {
+        Some(key) if !key.is_empty() => key.to_string(),
+        _ => load_nanobanana_api_key(&app, state.inner()).await?,
+    };
+
+    let nanobanana = load_settings(&app)?.nanobanana()?;
+    let url = format!("{}/{}", nanobanana.endpoint, nanobanana.model);
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("X-Goog-Api-Key", api_key)
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .map_err(|err| format!("Failed to reach the Gemini API: {err}"))?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(format!("The key can use {}.", nanobanana.model))
+    } else if matches!(status.as_u16(), 400 | 401 | 403) {
+        Err(format!("The Gemini API rejected the key (HTTP {status})."))
+    } else {
+        Err(format!(
+            "The Gemini API returned HTTP {status} while checking the key."
+        ))
+    }
 }
 
 #[tauri::command]
 async fn get_settings(app: AppHandle) -> Result<Settings, String> {
-    load_settings(&app)
+    Ok(load_settings(&app)?.masked())
+}
+
+/// Applies a partial settings change from the UI. Nothing is saved unless every field is
+/// valid, and the error names each rejected field.
+#[tauri::command]
+async fn update_settings(
+    app: AppHandle,
+    mut patch: settings::SettingsPatch,
+) -> Result<Settings, settings::SettingsUpdateError> {
+    let secrets = patch.take_secrets();
+    let model_ids = patch.model_ids();
+    let mut errors = Vec::new();
+    if !model_ids.is_empty() {
+        let client = inference_client(&app)?;
+        let backend = client.backend().name();
+        match client.list_models().await {
+            Ok(models) => {
+                for (field, model_id) in model_ids {
+                    if !inference::contains_model(&models, &model_id) {
+                        errors.push(settings::FieldError::new(
+                            field,
+                            format!(
+                                "\"{model_id}\" is not available in {backend}. {}",
+                                client.pull_hint(&model_id)
+                            ),
+                        ));
+                    }
+                }
+            }
+            Err(err) => {
+                for (field, _) in model_ids {
+                    errors.push(settings::FieldError::new(
+                        field,
+                        format!("Could not check the model against {backend}: {err}"),
+                    ));
+                }
+            }
+        }
+    }
+
+    settings::update(&app, |settings| {
+        errors.extend(settings.apply_patch(patch));
+        if !errors.is_empty() {
+            return Err(settings::SettingsUpdateError::invalid(errors));
+        }
+        Ok(())
+    })?;
+    if !secrets.is_empty() {
+        let vault = &app.state::<Arc<AppState>>().secrets;
+        for (entry, value) in &secrets {
+            vault.set(&app, entry, value.as_deref())?;
+        }
+        settings::republish(&app)?;
+    }
+    Ok(load_settings(&app)?.masked())
+}
+
+/// Reports every effective setting, which layer supplied it, and where the Nano Banana key
+/// comes from. Secrets are masked.
+#[tauri::command]
+async fn describe_config(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<settings::ConfigReport, String> {
+    let mut report = settings::describe(&app)?;
+    let key = resolve_nanobanana_api_key(&app, state.inner()).await.ok();
+    report.push(settings::ConfigEntry::secret(
+        "nanobananaApiKey",
+        key.map(|(_, source, origin)| (source, origin)),
+    ));
+    Ok(report)
+}
+
+/// Writes a settings bundle to the Downloads folder and returns its path. Secrets are only
+/// included, encrypted with `secrets_passphrase`, when a passphrase is given.
+#[tauri::command]
+async fn export_settings(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    secrets_passphrase: Option<String>,
+) -> Result<String, String> {
+    let settings = settings::load_user_settings(&app)?;
+    let passphrase = secrets_passphrase
+        .as_deref()
+        .filter(|value| !value.is_empty());
+    let bundle = settings::SettingsBundle::export(&settings, passphrase, |entry| {
+        state.secrets.get(&app, entry)
+    })?;
+    let contents = serde_json::to_string_pretty(&bundle)
+        .map_err(|err| format!("Failed to serialize settings bundle: {err}"))?;
+
+    let dir = app
+        .path()
+        .download_dir()
+        .or_else(|_| app.path().app_config_dir())
+        .map_err(|err| format!("Unable to resolve an export directory: {err}"))?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("slowly-unhinged-settings-{timestamp}.json"));
+    fs::write(&path, contents).map_err(|err| {
+        format!(
+            "Failed to write settings bundle to \"{}\": {err}",
+            path.display()
+        )
+    })?;
+
+    let message = format!(
+        "[Settings] Exported settings{} to {}.",
+        if passphrase.is_some() {
+            " with encrypted secrets"
+        } else {
+            ""
+        },
+        path.display()
+    );
+    println!("{message}");
+    emit_log(&app, LogLevel::Info, &message);
+    Ok(path.display().to_string())
+}
+
+/// Shows what importing `bundle` would change without saving anything.
+#[tauri::command]
+async fn preview_settings_import(
+    app: AppHandle,
+    bundle: String,
+    passphrase: Option<String>,
+) -> Result<settings::ImportPreview, String> {
+    let bundle = settings::SettingsBundle::parse(&bundle)?;
+    let plan = bundle.plan(&settings::load_user_settings(&app)?, passphrase.as_deref())?;
+    Ok(plan.preview)
+}
+
+#[tauri::command]
+async fn import_settings(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    bundle: String,
+    passphrase: Option<String>,
+) -> Result<settings::ImportPreview, String> {
+    let bundle = settings::SettingsBundle::parse(&bundle)?;
+    let plan = settings::update(&app, |settings| {
+        let plan = bundle.plan(settings, passphrase.as_deref())?;
+        *settings = plan.settings.clone();
+        Ok::<_, String>(plan)
+    })?;
+    for (entry, value) in &plan.vault_entries {
+        state.secrets.set(&app, entry, Some(value))?;
+    }
+    settings::republish(&app)?;
+
+    let message = format!(
+        "[Settings] Imported a settings bundle ({} changes).",
+        plan.preview.change_count()
+    );
+    println!("{message}");
+    emit_log(&app, LogLevel::Info, &message);
+    Ok(plan.preview)
 }
 
 #[tauri::command]
@@ -577,9 +1377,10 @@ async fn generate_background_image(
 
     let api_key = load_nanobanana_api_key(&app, state.inner()).await?;
 
+    let nanobanana = load_settings(&app)?.nanobanana()?;
     let url = format!(
-        "{}/{NANO_BANANA_MODEL}:generateContent",
-        NANO_BANANA_ENDPOINT
+        "{}/{}:generateContent",
+        nanobanana.endpoint, nanobanana.model
     );
 
     let last_asset = {
@@ -601,7 +1402,7 @@ async fn generate_background_image(
         "contents": [{"parts": parts}],
         "generationConfig": {
             "imageConfig": {
-                "aspectRatio": NANO_BANANA_ASPECT_RATIO
+                "aspectRatio": nanobanana.aspect_ratio
             }
         }
     });
@@ -681,7 +1482,7 @@ async fn generate_background_image(
 pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     let shared_state = Arc::new(AppState::new());
 
-    tauri::Builder::default()
+    let app = tauri::Builder::default()
         .manage(shared_state.clone())
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {            let state = app.state::<Arc<AppState>>().inner().clone();
@@ -690,19 +1491,69 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                     eprintln!("[HTTP] Companion API server terminated: {err}");
                 }
             });
+            let state = app.state::<Arc<AppState>>().inner().clone();
+            tauri::async_runtime::spawn(async move {
+                // Docker may not be running yet; the preflight tries again.
+                if let Err(err) = remove_orphaned_containers(&state).await {
+                    eprintln!("[Docker] Startup cleanup skipped: {err}");
+                }
+            });
+            if let Err(err) = settings::import_plaintext_keys(app.handle()) {
+                let message = format!("[Secrets] Plaintext keys were left in settings.json: {err}");
+                eprintln!("{message}");
+                emit_log(app.handle(), LogLevel::Error, &message);
+            }
+            if let Err(err) = settings::watch(app.handle().clone()) {
+                eprintln!("[Settings] Settings changes will need a restart: {err}");
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             register_agent,
             check_docker_access,
+            prepare_images,
+            cleanup_orphaned_containers,
             ensure_models_ready,
+            cancel_model_download,
+            get_inference_backend,
+            list_models,
+            chat_completion,
+            get_model_usage,
             stop_agent,
+            get_agent_status,
+            reset_agent_identity,
+            sign_in_with_zoom,
+            preview_screen_name,
+            sign_out_of_zoom,
+            get_tunnel_logs,
             save_wheel_state,
             load_wheel_state,
             get_settings,
+            update_settings,
+            describe_config,
+            export_settings,
+            preview_settings_import,
+            import_settings,
+            list_profiles,
+            create_profile,
+            switch_profile,
+            delete_profile,
+            set_nanobanana_api_key,
+            clear_nanobanana_api_key,
+            test_nanobanana_api_key,
             generate_background_image
         ])
-        .run(tauri::generate_context!())?;
+        .build(tauri::generate_context!())?;
+
+    app.run(|app_handle, event| {
+        if let tauri::RunEvent::Exit = event {
+            let state = app_handle.state::<Arc<AppState>>().inner().clone();
+            tauri::async_runtime::block_on(async {
+                heartbeat::stop(app_handle, &state).await;
+                unregister_agent(app_handle, &state).await;
+            });
+        }
+    });
 
     Ok(())
 }
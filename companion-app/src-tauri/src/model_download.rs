@@ -0,0 +1,288 @@
+//! Follows a model pull on the inference backend: progress with rate and ETA, stall detection,
+//! and cancellation.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tokio::{
+    sync::watch,
+    time::{interval, sleep, MissedTickBehavior},
+};
+
+use crate::inference::{InferenceClient, PullMessage};
+
+/// How far back the transfer rate looks.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+const TICK: Duration = Duration::from_secs(1);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+/// How long without new bytes before a download is reported as stalled. Capped at half the
+/// stall timeout.
+const STALL_NOTICE: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DownloadStatus {
+    Requested,
+    Downloading,
+    Stalled,
+    Complete,
+    Failed,
+    Cancelled,
+    /// Larger than the download budget.
+    Skipped,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ModelDownloadProgress {
+    pub model: String,
+    pub status: DownloadStatus,
+    pub pulled_bytes: u64,
+    pub total_bytes: u64,
+    pub percent: Option<f64>,
+    pub bytes_per_sec: Option<f64>,
+    pub eta_secs: Option<u64>,
+    pub message: String,
+}
+
+pub(crate) enum PullOutcome {
+    /// The backend reported the pull as successful.
+    Complete,
+    /// The stream ended without a verdict, as it does on releases that do not stream
+    /// progress. The caller has to check the model list.
+    Unconfirmed,
+}
+
+/// Pulls `model`, reporting progress through `on_progress`. Fails once no bytes arrive for
+/// `stall_timeout`, when `cancel` turns true, or as soon as the model turns out to be larger
+/// than `budget` bytes; a download that keeps moving is never timed out.
+pub(crate) async fn download(
+    client: &InferenceClient,
+    model: &str,
+    stall_timeout: Duration,
+    budget: Option<u64>,
+    mut cancel: watch::Receiver<bool>,
+    mut on_progress: impl FnMut(&ModelDownloadProgress),
+) -> Result<PullOutcome, String> {
+    let mut tracker = Tracker::new(model, stall_timeout);
+    on_progress(&tracker.report());
+
+    let mut pull = tokio::select! {
+        pull = client.pull_model(model) => pull.map_err(|err| {
+            tracker.fail(format!("Download request failed: {err}"), &mut on_progress)
+        })?,
+        _ = sleep(stall_timeout) => {
+            let message = format!(
+                "{} did not accept the download within {}s.",
+                client.backend().name(),
+                stall_timeout.as_secs()
+            );
+            return Err(tracker.fail(message, &mut on_progress));
+        }
+        _ = cancelled(&mut cancel) => return Err(tracker.cancel(&mut on_progress)),
+    };
+
+    let mut ticks = interval(TICK);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            message = pull.next() => match message {
+                Ok(Some(PullMessage::Progress { message, total, pulled })) => {
+                    if let Some(budget) = budget.filter(|&budget| total > budget) {
+                        return Err(tracker.skip(total, budget, &mut on_progress));
+                    }
+                    tracker.advance(total, pulled, message);
+                }
+                Ok(Some(PullMessage::Success { message })) => {
+                    tracker.complete(message, &mut on_progress);
+                    return Ok(PullOutcome::Complete);
+                }
+                Ok(Some(PullMessage::Error { message })) => {
+                    return Err(tracker.fail(message, &mut on_progress));
+                }
+                Ok(Some(PullMessage::Other)) => {}
+                Ok(None) => return Ok(PullOutcome::Unconfirmed),
+                Err(err) => return Err(tracker.fail(err.to_string(), &mut on_progress)),
+            },
+            _ = ticks.tick() => {}
+            _ = cancelled(&mut cancel) => return Err(tracker.cancel(&mut on_progress)),
+        }
+
+        if let Some(err) = tracker.check_stall() {
+            return Err(tracker.fail(err, &mut on_progress));
+        }
+        if tracker.due() {
+            on_progress(&tracker.report());
+        }
+    }
+}
+
+/// Resolves once `cancel` turns true. Never resolves if the sender is gone.
+async fn cancelled(cancel: &mut watch::Receiver<bool>) {
+    if cancel.wait_for(|&cancelled| cancelled).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+struct Tracker {
+    model: String,
+    status: DownloadStatus,
+    message: String,
+    total: u64,
+    pulled: u64,
+    samples: VecDeque<(Instant, u64)>,
+    last_advance: Instant,
+    last_report: Option<(Instant, DownloadStatus)>,
+    stall_notice: Duration,
+    stall_timeout: Duration,
+}
+
+impl Tracker {
+    fn new(model: &str, stall_timeout: Duration) -> Self {
+        Self {
+            model: model.to_string(),
+            status: DownloadStatus::Requested,
+            message: String::new(),
+            total: 0,
+            pulled: 0,
+            samples: VecDeque::new(),
+            last_advance: Instant::now(),
+            last_report: None,
+            stall_notice: STALL_NOTICE.min(stall_timeout / 2),
+            stall_timeout,
+        }
+    }
+
+    fn advance(&mut self, total: u64, pulled: u64, message: String) {
+        let now = Instant::now();
+        if pulled > self.pulled || self.samples.is_empty() {
+            self.last_advance = now;
+            self.samples.push_back((now, pulled));
+        }
+        if self.status != DownloadStatus::Downloading {
+            println!("[Inference] Downloading \"{}\": {message}", self.model);
+        }
+        self.status = DownloadStatus::Downloading;
+        self.total = total;
+        self.pulled = pulled;
+        self.message = message;
+    }
+
+    /// Flags a download that has gone quiet and returns an error once it has been quiet for
+    /// the whole stall timeout.
+    fn check_stall(&mut self) -> Option<String> {
+        let idle = self.last_advance.elapsed();
+        if idle >= self.stall_timeout {
+            return Some(format!(
+                "No data arrived for {}s; the download appears to be stuck.",
+                idle.as_secs()
+            ));
+        }
+        if idle >= self.stall_notice && self.status == DownloadStatus::Downloading {
+            println!(
+                "[Inference] Download of \"{}\" has received nothing for {}s.",
+                self.model,
+                idle.as_secs()
+            );
+            self.status = DownloadStatus::Stalled;
+        }
+        None
+    }
+
+    /// Whether a progress event is due: on every status change, otherwise at most every
+    /// `PROGRESS_INTERVAL`.
+    fn due(&self) -> bool {
+        match self.last_report {
+            Some((at, status)) => status != self.status || at.elapsed() >= PROGRESS_INTERVAL,
+            None => true,
+        }
+    }
+
+    fn complete(&mut self, message: String, on_progress: &mut impl FnMut(&ModelDownloadProgress)) {
+        println!("[Inference] Downloaded \"{}\".", self.model);
+        self.status = DownloadStatus::Complete;
+        self.pulled = self.pulled.max(self.total);
+        self.message = message;
+        on_progress(&self.report());
+    }
+
+    fn fail(
+        &mut self,
+        message: String,
+        on_progress: &mut impl FnMut(&ModelDownloadProgress),
+    ) -> String {
+        eprintln!(
+            "[Inference] Download of \"{}\" failed: {message}",
+            self.model
+        );
+        self.status = DownloadStatus::Failed;
+        self.message = message.clone();
+        on_progress(&self.report());
+        message
+    }
+
+    fn cancel(&mut self, on_progress: &mut impl FnMut(&ModelDownloadProgress)) -> String {
+        println!("[Inference] Download of \"{}\" was cancelled.", self.model);
+        self.status = DownloadStatus::Cancelled;
+        self.message = "Cancelled.".to_string();
+        on_progress(&self.report());
+        format!("The download of \"{}\" was cancelled.", self.model)
+    }
+
+    fn skip(
+        &mut self,
+        total: u64,
+        budget: u64,
+        on_progress: &mut impl FnMut(&ModelDownloadProgress),
+    ) -> String {
+        let message = format!(
+            "The model is {} MB, more than the {} MB download budget.",
+            total.div_ceil(1024 * 1024),
+            budget / (1024 * 1024)
+        );
+        println!("[Inference] Not downloading \"{}\": {message}", self.model);
+        self.status = DownloadStatus::Skipped;
+        self.total = total;
+        self.message = message.clone();
+        on_progress(&self.report());
+        message
+    }
+
+    /// Bytes per second over the last `RATE_WINDOW`, measured up to now so that the rate falls
+    /// while no data arrives.
+    fn rate(&mut self) -> Option<f64> {
+        let now = Instant::now();
+        while self.samples.len() > 1 && now.duration_since(self.samples[1].0) >= RATE_WINDOW {
+            self.samples.pop_front();
+        }
+        let &(since, pulled) = self.samples.front()?;
+        let elapsed = now.duration_since(since).as_secs_f64();
+        (elapsed >= 1.0).then(|| self.pulled.saturating_sub(pulled) as f64 / elapsed)
+    }
+
+    fn report(&mut self) -> ModelDownloadProgress {
+        let bytes_per_sec = match self.status {
+            DownloadStatus::Downloading | DownloadStatus::Stalled => self.rate(),
+            _ => None,
+        };
+        let remaining = self.total.saturating_sub(self.pulled);
+        let eta_secs = bytes_per_sec
+            .filter(|&rate| rate > 0.0 && self.total > 0)
+            .map(|rate| (remaining as f64 / rate).ceil() as u64);
+        self.last_report = Some((Instant::now(), self.status));
+        ModelDownloadProgress {
+            model: self.model.clone(),
+            status: self.status,
+            pulled_bytes: self.pulled,
+            total_bytes: self.total,
+            percent: (self.total > 0)
+                .then(|| (self.pulled as f64 / self.total as f64 * 100.0).min(100.0)),
+            bytes_per_sec,
+            eta_secs,
+            message: self.message.clone(),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+const SCREEN_NAME_MIN_CHARS: usize = 3;
+const SCREEN_NAME_MAX_CHARS: usize = 64;
+const ALLOWED_PUNCTUATION: &[char] = &[' ', '@', '.', '-', '_', '\'', '(', ')', '&'];
+
+/// A validated screen name. `normalized` is the identity the Hub keys registrations
+/// by; hub/screen_name.js applies the same steps in `normalizeScreenName`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ScreenName {
+    display: String,
+    normalized: String,
+}
+
+impl ScreenName {
+    /// Applies NFKC, collapses whitespace, checks the allowed characters and length,
+    /// then case-folds (upper- then lower-casing, which JavaScript can reproduce) and
+    /// re-applies NFKC.
+    pub(crate) fn parse(raw: &str) -> Result<Self, String> {
+        let composed: String = raw.nfkc().collect();
+        let display = composed.split_whitespace().collect::<Vec<_>>().join(" ");
+        if display.is_empty() {
+            return Err("Screen name must not be empty.".to_string());
+        }
+
+        if let Some(invalid) = display.chars().find(|&c| !is_allowed(c)) {
+            return Err(format!(
+                "Screen name contains an unsupported character {invalid:?} (U+{:04X}). \
+                 Use letters, digits, spaces, and @ . - _ ' ( ) &.",
+                invalid as u32
+            ));
+        }
+
+        let normalized: String = display.to_uppercase().to_lowercase().nfkc().collect();
+        let length = normalized.chars().count();
+        if !(SCREEN_NAME_MIN_CHARS..=SCREEN_NAME_MAX_CHARS).contains(&length) {
+            return Err(format!(
+                "Screen name must be between {SCREEN_NAME_MIN_CHARS} and {SCREEN_NAME_MAX_CHARS} characters (got {length})."
+            ));
+        }
+
+        Ok(Self {
+            display,
+            normalized,
+        })
+    }
+
+    pub(crate) fn normalized(&self) -> &str {
+        &self.normalized
+    }
+}
+
+fn is_allowed(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c) || ALLOWED_PUNCTUATION.contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::ScreenName;
+
+    /// Shared with hub/test/screen_name.test.js so the companion and the Hub agree.
+    const VECTORS: &str = include_str!("../../../hub/test/screen_name_vectors.json");
+
+    #[derive(Deserialize)]
+    struct Vector {
+        name: String,
+        input: String,
+        display: Option<String>,
+        normalized: Option<String>,
+        error: Option<String>,
+    }
+
+    #[test]
+    fn parse_matches_shared_vectors() {
+        let vectors: Vec<Vector> = serde_json::from_str(VECTORS).unwrap();
+        for vector in vectors {
+            let parsed = ScreenName::parse(&vector.input);
+            match vector.error.as_deref() {
+                None => {
+                    let parsed = parsed.unwrap_or_else(|err| panic!("{}: {err}", vector.name));
+                    assert_eq!(Some(parsed.display), vector.display, "{}", vector.name);
+                    assert_eq!(
+                        Some(parsed.normalized),
+                        vector.normalized,
+                        "{}",
+                        vector.name
+                    );
+                }
+                Some(kind) => {
+                    let err = parsed
+                        .err()
+                        .unwrap_or_else(|| panic!("{}: accepted", vector.name));
+                    let expected = match kind {
+                        "empty" => "must not be empty",
+                        "length" => "must be between",
+                        "character" => "unsupported character",
+                        other => panic!("{}: unknown error kind {other}", vector.name),
+                    };
+                    assert!(err.contains(expected), "{}: {err}", vector.name);
+                }
+            }
+        }
+    }
+}
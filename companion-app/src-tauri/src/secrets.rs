@@ -0,0 +1,436 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{settings::Settings, AppState};
+
+pub(crate) const VAULT_PASSPHRASE_ENV: &str = "SLOWLY_UNHINGED_VAULT_PASSPHRASE";
+/// Vault entries for the secrets that older settings kept in `settings.json`.
+pub(crate) const ZOOM_CLIENT_SECRET: &str = "zoomClientSecret";
+pub(crate) const INFERENCE_API_KEY: &str = "inferenceApiKey";
+/// The settings fields that live in the vault, stored under the field's own name.
+pub(crate) const SETTINGS_SECRETS: [&str; 2] = [ZOOM_CLIENT_SECRET, INFERENCE_API_KEY];
+/// The machine-wide Nano Banana key, imported from the legacy `nanobanana_api_key.txt`.
+pub(crate) const SHARED_NANOBANANA_KEY: &str = "nanobanana";
+const VAULT_FILE_NAME: &str = "secrets.json";
+const MACHINE_KEY_FILE_NAME: &str = "secrets.key";
+const LEGACY_KEY_FILE_NAME: &str = "nanobanana_api_key.txt";
+const VAULT_FILE_VERSION: u32 = 1;
+const VAULT_AAD: &[u8] = b"slowly-unhinged-secrets-v1";
+const BUNDLE_AAD: &[u8] = b"slowly-unhinged-bundle-secrets-v1";
+const MIN_BUNDLE_PASSPHRASE_CHARS: usize = 8;
+const KEY_LENGTH: usize = 32;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+
+/// Vault entry holding a profile's own Nano Banana key.
+pub(crate) fn profile_nanobanana_key(profile: &str) -> String {
+    format!("nanobanana/{profile}")
+}
+
+/// How the vault key is obtained. Stored unencrypted next to the ciphertext.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum KeyDerivation {
+    /// A random key in `secrets.key`, readable only by the current user.
+    MachineKey,
+    /// Argon2id over the passphrase in `SLOWLY_UNHINGED_VAULT_PASSPHRASE`.
+    Passphrase { salt: String },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultFile {
+    version: u32,
+    kdf: KeyDerivation,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Encrypted store for API keys, kept apart from `settings.json` so settings can be shared
+/// or edited without exposing secrets.
+#[derive(Default)]
+pub(crate) struct SecretsVault {
+    // Deriving a passphrase key is deliberately slow, so keep the result for the session.
+    key: Mutex<Option<(KeyDerivation, [u8; KEY_LENGTH])>>,
+    // Held for every read-modify-write of the vault so concurrent changes cannot drop each
+    // other.
+    io: Mutex<()>,
+}
+
+impl SecretsVault {
+    pub(crate) fn get(&self, app: &AppHandle, name: &str) -> Result<Option<String>, String> {
+        Ok(self.read(app)?.remove(name))
+    }
+
+    pub(crate) fn contains(&self, app: &AppHandle, name: &str) -> Result<bool, String> {
+        Ok(self.read(app)?.contains_key(name))
+    }
+
+    /// Stores `value` under `name`, or removes the entry when `value` is `None`.
+    pub(crate) fn set(
+        &self,
+        app: &AppHandle,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<(), String> {
+        let _io = self.io.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut entries = self.read(app)?;
+        let changed = match value {
+            Some(value) => {
+                entries
+                    .insert(name.to_string(), value.to_string())
+                    .as_deref()
+                    != Some(value)
+            }
+            None => entries.remove(name).is_some(),
+        };
+        if changed {
+            self.write(app, &entries)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self, app: &AppHandle) -> Result<BTreeMap<String, String>, String> {
+        let path = vault_path(app)?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => return Err(format!("Failed to read the secrets vault: {err}")),
+        };
+        let file: VaultFile = serde_json::from_str(&contents)
+            .map_err(|err| format!("Failed to parse the secrets vault: {err}"))?;
+        if file.version != VAULT_FILE_VERSION {
+            return Err(format!(
+                "The secrets vault has unsupported version {}.",
+                file.version
+            ));
+        }
+
+        let key = self.key(app, &file.kdf)?;
+        let plaintext =
+            decrypt(&key, &file.nonce, &file.ciphertext, VAULT_AAD)?.ok_or_else(|| {
+                format!(
+                    "Failed to decrypt the secrets vault at \"{}\": {}",
+                    path.display(),
+                    match file.kdf {
+                        KeyDerivation::MachineKey => "secrets.key does not match it.",
+                        KeyDerivation::Passphrase { .. } => "the passphrase is wrong.",
+                    }
+                )
+            })?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|err| format!("Failed to parse the decrypted secrets vault: {err}"))
+    }
+
+    fn write(&self, app: &AppHandle, entries: &BTreeMap<String, String>) -> Result<(), String> {
+        let path = vault_path(app)?;
+        // Keep the existing key derivation so a passphrase vault stays a passphrase vault.
+        let kdf = match fs::read_to_string(&path) {
+            Ok(contents) => {
+                serde_json::from_str::<VaultFile>(&contents)
+                    .map_err(|err| format!("Failed to parse the secrets vault: {err}"))?
+                    .kdf
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => new_key_derivation(),
+            Err(err) => return Err(format!("Failed to read the secrets vault: {err}")),
+        };
+        let key = self.key(app, &kdf)?;
+
+        let plaintext = serde_json::to_vec(entries)
+            .map_err(|err| format!("Failed to serialize secrets: {err}"))?;
+        let (nonce, ciphertext) = encrypt(&key, &plaintext, VAULT_AAD)?;
+        let file = VaultFile {
+            version: VAULT_FILE_VERSION,
+            kdf,
+            nonce,
+            ciphertext,
+        };
+        let contents = serde_json::to_string_pretty(&file)
+            .map_err(|err| format!("Failed to serialize the secrets vault: {err}"))?;
+        write_private(&path, contents.as_bytes())
+    }
+
+    fn key(&self, app: &AppHandle, kdf: &KeyDerivation) -> Result<[u8; KEY_LENGTH], String> {
+        let mut cached = self.key.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((cached_kdf, key)) = cached.as_ref() {
+            if cached_kdf == kdf {
+                return Ok(*key);
+            }
+        }
+        let key = match kdf {
+            KeyDerivation::MachineKey => machine_key(app)?,
+            KeyDerivation::Passphrase { salt } => {
+                let passphrase = env::var(VAULT_PASSPHRASE_ENV)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| {
+                        format!(
+                            "The secrets vault is locked with a passphrase. Set {VAULT_PASSPHRASE_ENV} and restart the app."
+                        )
+                    })?;
+                derive_key(&passphrase, &decode(salt, "salt")?)?
+            }
+        };
+        cached.replace((kdf.clone(), key));
+        Ok(key)
+    }
+}
+
+/// Secrets encrypted with a passphrase so they can travel inside a settings bundle.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SealedSecrets {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl SealedSecrets {
+    pub(crate) fn seal(passphrase: &str, plaintext: &[u8]) -> Result<Self, String> {
+        if passphrase.chars().count() < MIN_BUNDLE_PASSPHRASE_CHARS {
+            return Err(format!(
+                "Use a passphrase of at least {MIN_BUNDLE_PASSPHRASE_CHARS} characters to export secrets."
+            ));
+        }
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt)?;
+        let (nonce, ciphertext) = encrypt(&key, plaintext, BUNDLE_AAD)?;
+        Ok(Self {
+            salt: BASE64_STANDARD.encode(salt),
+            nonce,
+            ciphertext,
+        })
+    }
+
+    pub(crate) fn open(&self, passphrase: &str) -> Result<Vec<u8>, String> {
+        let key = derive_key(passphrase, &decode(&self.salt, "salt")?)?;
+        decrypt(&key, &self.nonce, &self.ciphertext, BUNDLE_AAD)?
+            .ok_or_else(|| "The passphrase does not unlock the secrets in this bundle.".to_string())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LENGTH], String> {
+    let mut key = [0u8; KEY_LENGTH];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| format!("Failed to derive an encryption key: {err}"))?;
+    Ok(key)
+}
+
+/// Returns the base64 nonce and ciphertext.
+fn encrypt(
+    key: &[u8; KEY_LENGTH],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(String, String), String> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = XChaCha20Poly1305::new(key.into())
+        .encrypt(
+            &XNonce::from(nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| "Failed to encrypt secrets.".to_string())?;
+    Ok((
+        BASE64_STANDARD.encode(nonce),
+        BASE64_STANDARD.encode(ciphertext),
+    ))
+}
+
+/// Returns `None` when the key does not match or the ciphertext was tampered with.
+fn decrypt(
+    key: &[u8; KEY_LENGTH],
+    nonce: &str,
+    ciphertext: &str,
+    aad: &[u8],
+) -> Result<Option<Vec<u8>>, String> {
+    let nonce: [u8; NONCE_LENGTH] = decode(nonce, "nonce")?
+        .try_into()
+        .map_err(|_| "Encrypted secrets have a malformed nonce.".to_string())?;
+    Ok(XChaCha20Poly1305::new(key.into())
+        .decrypt(
+            &XNonce::from(nonce),
+            Payload {
+                msg: &decode(ciphertext, "ciphertext")?,
+                aad,
+            },
+        )
+        .ok())
+}
+
+fn new_key_derivation() -> KeyDerivation {
+    if env::var(VAULT_PASSPHRASE_ENV).is_ok_and(|value| !value.is_empty()) {
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        KeyDerivation::Passphrase {
+            salt: BASE64_STANDARD.encode(salt),
+        }
+    } else {
+        KeyDerivation::MachineKey
+    }
+}
+
+fn machine_key(app: &AppHandle) -> Result<[u8; KEY_LENGTH], String> {
+    let path = config_file(app, MACHINE_KEY_FILE_NAME)?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => decode(contents.trim(), "secrets.key")?
+            .try_into()
+            .map_err(|_| format!("\"{}\" has the wrong length.", path.display())),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            // A new key would silently orphan every secret already encrypted with the old one.
+            let vault = vault_path(app)?;
+            if vault.try_exists().unwrap_or(true) {
+                return Err(format!(
+                    "The vault key \"{}\" is missing, so the secrets in \"{}\" cannot be decrypted. Restore secrets.key from a backup, or delete secrets.json and enter the keys again.",
+                    path.display(),
+                    vault.display()
+                ));
+            }
+            let mut key = [0u8; KEY_LENGTH];
+            OsRng.fill_bytes(&mut key);
+            write_private(&path, BASE64_STANDARD.encode(key).as_bytes())?;
+            println!("[Secrets] Created vault key at {}.", path.display());
+            Ok(key)
+        }
+        Err(err) => Err(format!(
+            "Failed to read the vault key from \"{}\": {err}",
+            path.display()
+        )),
+    }
+}
+
+fn decode(value: &str, field: &str) -> Result<Vec<u8>, String> {
+    BASE64_STANDARD
+        .decode(value)
+        .map_err(|err| format!("Encrypted secrets have a malformed {field}: {err}"))
+}
+
+/// Writes through a synced temporary file that only the current user can ever read, then
+/// renames it over `path`, so the contents are neither exposed nor left half-written.
+pub(crate) fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let write = || -> std::io::Result<()> {
+        // A file left behind by a crash may have other permissions, so always start afresh.
+        match fs::remove_file(&temp_path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            let _ = fs::File::open(dir).and_then(|dir| dir.sync_all());
+        }
+        Ok(())
+    };
+    write().map_err(|err| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to write \"{}\": {err}", path.display())
+    })
+}
+
+fn config_file(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let mut dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|err| format!("Unable to resolve config directory: {err}"))?;
+
+    fs::create_dir_all(&dir).map_err(|err| format!("Unable to create config directory: {err}"))?;
+
+    dir.push(name);
+    Ok(dir)
+}
+
+fn vault_path(app: &AppHandle) -> Result<PathBuf, String> {
+    config_file(app, VAULT_FILE_NAME)
+}
+
+pub(crate) fn legacy_key_file_path(app: &AppHandle) -> Result<PathBuf, String> {
+    config_file(app, LEGACY_KEY_FILE_NAME)
+}
+
+/// Moves plaintext keys from profiles, the Zoom client secret and inference API key, and
+/// `nanobanana_api_key.txt` into the vault. Returns whether `settings` changed and need saving.
+pub(crate) fn import_plaintext_keys(
+    app: &AppHandle,
+    settings: &mut Settings,
+) -> Result<bool, String> {
+    let vault = &app.state::<Arc<AppState>>().secrets;
+    let mut changed = false;
+    for (name, profile) in settings.profiles_mut() {
+        let Some(key) = profile.nanobanana_api_key.take() else {
+            continue;
+        };
+        let key = key.trim();
+        if !key.is_empty() {
+            vault.set(app, &profile_nanobanana_key(name), Some(key))?;
+            println!("[Secrets] Moved the Nano Banana key of profile \"{name}\" into the vault.");
+        }
+        changed = true;
+    }
+    for (entry, value) in settings.take_plaintext_secrets() {
+        vault.set(app, entry, Some(&value))?;
+        println!("[Secrets] Moved \"{entry}\" from settings.json into the vault.");
+        changed = true;
+    }
+
+    let legacy_path = legacy_key_file_path(app)?;
+    match fs::read_to_string(&legacy_path) {
+        Ok(contents) => {
+            let key = contents.trim();
+            if !key.is_empty() {
+                vault.set(app, SHARED_NANOBANANA_KEY, Some(key))?;
+            }
+            fs::remove_file(&legacy_path).map_err(|err| {
+                format!(
+                    "Failed to remove \"{}\" after moving it into the vault: {err}",
+                    legacy_path.display()
+                )
+            })?;
+            println!(
+                "[Secrets] Moved the key from {} into the vault.",
+                legacy_path.display()
+            );
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(format!(
+                "Failed to read \"{}\": {err}",
+                legacy_path.display()
+            ))
+        }
+    }
+
+    Ok(changed)
+}
@@ -0,0 +1,1793 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    io::{ErrorKind, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::{sync::mpsc, time::sleep};
+
+use crate::{docker, emit_log, hub, secrets, zoom_auth, AppState, LogLevel};
+
+mod bundle;
+mod layers;
+
+pub(crate) use bundle::{ImportPreview, SettingsBundle};
+pub(crate) use layers::{ConfigEntry, ConfigReport, ConfigSource};
+
+/// Docker Model Runner's default models. The other backends have none.
+const DEFAULT_TRANSCRIPTION_MODEL_ID: &str = "hf.co/ggml-org/ultravox-v0_5-llama-3_1-8b-gguf";
+const DEFAULT_BACKGROUND_PROMPT_MODEL_ID: &str = "hf.co/unsloth/gemma-3n-e2b-it-gguf:q8_k_xl";
+const DEFAULT_HUB_HEARTBEAT_INTERVAL_SECS: u64 = 60;
+const HUB_HEARTBEAT_INTERVAL_RANGE_SECS: RangeInclusive<u64> = 10..=240;
+const DEFAULT_DMR_BASE_URL: &str = "http://localhost:12434";
+const DEFAULT_LLAMA_CPP_BASE_URL: &str = "http://localhost:8080";
+const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+const DEFAULT_DMR_WARMUP_ATTEMPTS: u64 = 10;
+const DMR_WARMUP_ATTEMPTS_RANGE: RangeInclusive<u64> = 1..=120;
+const DEFAULT_DMR_MODEL_POLL_ATTEMPTS: u64 = 60;
+const DMR_MODEL_POLL_ATTEMPTS_RANGE: RangeInclusive<u64> = 1..=1_440;
+const DEFAULT_DMR_MODEL_POLL_DELAY_MS: u64 = 5_000;
+const DMR_MODEL_POLL_DELAY_RANGE_MS: RangeInclusive<u64> = 500..=60_000;
+const DEFAULT_DMR_PULL_STALL_TIMEOUT_SECS: u64 = 120;
+const DMR_PULL_STALL_TIMEOUT_RANGE_SECS: RangeInclusive<u64> = 15..=1_800;
+const MODEL_DOWNLOAD_BUDGET_RANGE_MB: RangeInclusive<u64> = 100..=1_048_576;
+const MAX_MODEL_FALLBACKS: usize = 8;
+const DEFAULT_NANOBANANA_MODEL: &str = "gemini-2.5-flash-image";
+const DEFAULT_NANOBANANA_ENDPOINT: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const DEFAULT_NANOBANANA_ASPECT_RATIO: &str = "16:9";
+/// The aspect ratios Gemini image models accept.
+const NANOBANANA_ASPECT_RATIOS: &[&str] = &[
+    "1:1", "2:3", "3:2", "3:4", "4:3", "4:5", "5:4", "9:16", "16:9", "21:9",
+];
+pub(crate) const DEFAULT_BACKGROUND_LONG_POLL_TIMEOUT_SECS: u64 = 25;
+const BACKGROUND_LONG_POLL_TIMEOUT_RANGE_SECS: RangeInclusive<u64> = 1..=120;
+const DEFAULT_PROFILE_NAME: &str = "Default";
+/// Bump together with a new entry in `MIGRATIONS`.
+const SETTINGS_SCHEMA_VERSION: u32 = 1;
+const PROFILE_NAME_MAX_CHARS: usize = 40;
+const BACKGROUND_STYLE_MAX_CHARS: usize = 200;
+const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+const SETTINGS_RECOVERED_EVENT: &str = "settings-recovered";
+/// How many previous versions of `settings.json` are kept as `settings.backup.<n>.json`.
+const SETTINGS_BACKUP_COUNT: usize = 5;
+/// Editors often write a file in several steps; wait for them to finish before reloading.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WheelState {
+    positions: Vec<usize>,
+    active_index: usize,
+}
+
+/// Where a profile looks for its nano banana API key.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum NanobananaKeySource {
+    /// The profile's own key, then `NANOBANANA_API_KEY`, then the Hub-delivered key,
+    /// then the legacy key file.
+    #[default]
+    Auto,
+    Settings,
+    Environment,
+    File,
+    Hub,
+}
+
+/// The server that runs the speech and prompt models.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum InferenceBackend {
+    #[default]
+    Dmr,
+    /// llama.cpp's `llama-server`, which serves the models it was started with.
+    LlamaCpp,
+    Ollama,
+    /// Any server with OpenAI's `/models` and `/chat/completions` under a `/v1` base URL.
+    OpenAiCompatible,
+}
+
+impl InferenceBackend {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Dmr => "Docker Model Runner",
+            Self::LlamaCpp => "the llama.cpp server",
+            Self::Ollama => "Ollama",
+            Self::OpenAiCompatible => "the OpenAI-compatible endpoint",
+        }
+    }
+
+    /// The model for `role` when the profile names none. Only Docker Model Runner can pull a
+    /// known model; the other backends serve what the user installed, so the user must pick.
+    pub(crate) fn default_model(self, role: ModelRole) -> Option<&'static str> {
+        match (self, role) {
+            (Self::Dmr, ModelRole::Transcription) => Some(DEFAULT_TRANSCRIPTION_MODEL_ID),
+            (Self::Dmr, ModelRole::Prompt) => Some(DEFAULT_BACKGROUND_PROMPT_MODEL_ID),
+            _ => None,
+        }
+    }
+
+    /// The OpenAI-compatible backend has no default and needs `inferenceBaseUrl`.
+    fn default_base_url(self) -> Option<&'static str> {
+        match self {
+            Self::Dmr => Some(DEFAULT_DMR_BASE_URL),
+            Self::LlamaCpp => Some(DEFAULT_LLAMA_CPP_BASE_URL),
+            Self::Ollama => Some(DEFAULT_OLLAMA_BASE_URL),
+            Self::OpenAiCompatible => None,
+        }
+    }
+}
+
+/// Everything that differs between people or orgs sharing one machine.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Profile {
+    #[serde(default)]
+    pub(crate) wheels: Option<WheelState>,
+    /// Normalized identity of the last screen name the agent started with.
+    #[serde(default)]
+    pub(crate) screen_name: Option<String>,
+    #[serde(default)]
+    pub(crate) model_transcription: Option<String>,
+    #[serde(default)]
+    pub(crate) model_prompt: Option<String>,
+    /// Models to try, in order, when `model_transcription` is missing and cannot be pulled.
+    #[serde(default)]
+    pub(crate) model_transcription_fallbacks: Vec<String>,
+    #[serde(default)]
+    pub(crate) model_prompt_fallbacks: Vec<String>,
+    /// Extra guidance for background prompts, such as "watercolor, muted palette".
+    #[serde(default)]
+    pub(crate) background_style: Option<String>,
+    #[serde(default)]
+    pub(crate) nanobanana_key_source: NanobananaKeySource,
+    /// Plaintext key from older settings. It stays in the file until the startup import
+    /// moves it into the secrets vault, and is never copied into backups.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) nanobanana_api_key: Option<String>,
+}
+
+impl Profile {
+    /// The models that can fill `role` on `backend`, most preferred first. Empty when the
+    /// profile names no model and the backend has no default.
+    pub(crate) fn model_candidates(
+        &self,
+        role: ModelRole,
+        backend: InferenceBackend,
+    ) -> Vec<String> {
+        let (preferred, fallbacks) = match role {
+            ModelRole::Transcription => (
+                &self.model_transcription,
+                &self.model_transcription_fallbacks,
+            ),
+            ModelRole::Prompt => (&self.model_prompt, &self.model_prompt_fallbacks),
+        };
+        let mut candidates: Vec<String> = preferred
+            .clone()
+            .or_else(|| backend.default_model(role).map(str::to_string))
+            .into_iter()
+            .collect();
+        for model in fallbacks {
+            if !candidates.contains(model) {
+                candidates.push(model.clone());
+            }
+        }
+        candidates
+    }
+}
+
+/// What the companion uses a model for.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ModelRole {
+    Transcription,
+    Prompt,
+}
+
+impl ModelRole {
+    pub(crate) const ALL: [ModelRole; 2] = [ModelRole::Transcription, ModelRole::Prompt];
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProfileSummary {
+    name: String,
+    screen_name: Option<String>,
+    active: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Settings {
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default)]
+    active_profile: String,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+    #[serde(default)]
+    cloudflared_image: Option<docker::ContainerImage>,
+    #[serde(default)]
+    hub_url: Option<String>,
+    #[serde(default)]
+    hub_register_path: Option<String>,
+    #[serde(default)]
+    hub_unregister_path: Option<String>,
+    #[serde(default)]
+    hub_heartbeat_path: Option<String>,
+    #[serde(default)]
+    hub_release_identity_path: Option<String>,
+    #[serde(default)]
+    hub_heartbeat_interval_secs: Option<u64>,
+    #[serde(default)]
+    zoom_client_id: Option<String>,
+    /// Supplied by the other layers. The user's own secret lives in the vault; a value in
+    /// `settings.json` is from older settings and is moved there by the startup import.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zoom_client_secret: Option<String>,
+    #[serde(default)]
+    zoom_authorize_url: Option<String>,
+    #[serde(default)]
+    zoom_token_url: Option<String>,
+    #[serde(default)]
+    zoom_user_url: Option<String>,
+    #[serde(default)]
+    inference_backend: Option<InferenceBackend>,
+    /// Overrides the backend's default URL, and `dmr_base_url` for Docker Model Runner.
+    #[serde(default)]
+    inference_base_url: Option<String>,
+    /// Sent as a bearer token to every backend. Kept in the vault like `zoom_client_secret`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inference_api_key: Option<String>,
+    #[serde(default)]
+    dmr_base_url: Option<String>,
+    #[serde(default)]
+    dmr_warmup_attempts: Option<u64>,
+    #[serde(default)]
+    dmr_model_poll_attempts: Option<u64>,
+    #[serde(default)]
+    dmr_model_poll_delay_ms: Option<u64>,
+    #[serde(default)]
+    dmr_pull_stall_timeout_secs: Option<u64>,
+    /// Largest model, in megabytes, the companion pulls on its own. Unlimited when unset.
+    #[serde(default)]
+    model_download_budget_mb: Option<u64>,
+    #[serde(default)]
+    nanobanana_model: Option<String>,
+    #[serde(default)]
+    nanobanana_endpoint: Option<String>,
+    #[serde(default)]
+    nanobanana_aspect_ratio: Option<String>,
+    #[serde(default)]
+    background_long_poll_timeout_secs: Option<u64>,
+}
+
+/// Which inference backend to use, where it listens, and how long the companion waits on it.
+pub(crate) struct InferenceConfig {
+    pub(crate) backend: InferenceBackend,
+    pub(crate) base_url: String,
+    pub(crate) api_key: Option<String>,
+    pub(crate) warmup_attempts: usize,
+    pub(crate) model_poll_attempts: usize,
+    pub(crate) model_poll_delay: Duration,
+    pub(crate) pull_stall_timeout: Duration,
+    /// In bytes.
+    pub(crate) download_budget: Option<u64>,
+}
+
+/// The Gemini image model used for background images.
+pub(crate) struct NanobananaConfig {
+    pub(crate) model: String,
+    pub(crate) endpoint: String,
+    pub(crate) aspect_ratio: String,
+}
+
+impl Settings {
+    /// Guarantees at least one profile and a valid active profile. Returns whether the
+    /// settings changed.
+    fn ensure_profiles(&mut self) -> bool {
+        let mut changed = false;
+        if self.profiles.is_empty() {
+            self.profiles
+                .insert(DEFAULT_PROFILE_NAME.to_string(), Profile::default());
+            changed = true;
+        }
+        if !self.profiles.contains_key(&self.active_profile) {
+            self.active_profile = self
+                .profiles
+                .keys()
+                .next()
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_PROFILE_NAME.to_string());
+            changed = true;
+        }
+        changed
+    }
+
+    pub(crate) fn active_profile_name(&self) -> &str {
+        &self.active_profile
+    }
+
+    pub(crate) fn active_profile(&self) -> &Profile {
+        self.profiles
+            .get(&self.active_profile)
+            .expect("settings always hold the active profile after loading")
+    }
+
+    pub(crate) fn active_profile_mut(&mut self) -> &mut Profile {
+        self.profiles
+            .get_mut(&self.active_profile)
+            .expect("settings always hold the active profile after loading")
+    }
+
+    pub(crate) fn profiles_mut(&mut self) -> impl Iterator<Item = (&String, &mut Profile)> {
+        self.profiles.iter_mut()
+    }
+
+    pub(crate) fn profile_summaries(&self) -> Vec<ProfileSummary> {
+        self.profiles
+            .iter()
+            .map(|(name, profile)| ProfileSummary {
+                name: name.clone(),
+                screen_name: profile.screen_name.clone(),
+                active: *name == self.active_profile,
+            })
+            .collect()
+    }
+
+    /// Resolves `name` to an existing profile name, ignoring case.
+    pub(crate) fn find_profile(&self, name: &str) -> Option<String> {
+        let name = name.trim();
+        self.profiles
+            .keys()
+            .find(|existing| existing.to_lowercase() == name.to_lowercase())
+            .cloned()
+    }
+
+    /// Adds a profile, copying `copy_from` when given and defaults otherwise.
+    pub(crate) fn create_profile(
+        &mut self,
+        name: &str,
+        copy_from: Option<&str>,
+    ) -> Result<(), String> {
+        let name = name.trim();
+        let length = name.chars().count();
+        if length == 0 || length > PROFILE_NAME_MAX_CHARS || name.contains(char::is_control) {
+            return Err(format!(
+                "Profile names must be 1 to {PROFILE_NAME_MAX_CHARS} characters without control characters."
+            ));
+        }
+        if let Some(existing) = self.find_profile(name) {
+            return Err(format!("A profile named \"{existing}\" already exists."));
+        }
+
+        let profile = match copy_from {
+            Some(source) => {
+                let source_name = self
+                    .find_profile(source)
+                    .ok_or_else(|| format!("Profile \"{source}\" does not exist."))?;
+                let mut copy = self.profiles[&source_name].clone();
+                // The screen name is the identity; a copy starts without one.
+                copy.screen_name = None;
+                copy.wheels = None;
+                copy
+            }
+            None => Profile::default(),
+        };
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
+
+    pub(crate) fn switch_profile(&mut self, name: &str) -> Result<(), String> {
+        let name = self
+            .find_profile(name)
+            .ok_or_else(|| format!("Profile \"{}\" does not exist.", name.trim()))?;
+        self.active_profile = name;
+        Ok(())
+    }
+
+    pub(crate) fn delete_profile(&mut self, name: &str) -> Result<(), String> {
+        let name = self
+            .find_profile(name)
+            .ok_or_else(|| format!("Profile \"{}\" does not exist.", name.trim()))?;
+        if name == self.active_profile {
+            return Err(format!(
+                "Profile \"{name}\" is active. Switch to another profile before deleting it."
+            ));
+        }
+        self.profiles.remove(&name);
+        Ok(())
+    }
+
+    /// Rejects settings the app cannot run with. Hub settings are only checked when the agent
+    /// uses them, so that a bad Hub URL does not keep the rest of the app from loading.
+    fn validate(&self) -> Result<(), String> {
+        self.inference()?;
+        self.nanobanana()?;
+        self.background_long_poll_timeout()?;
+        for (name, profile) in &self.profiles {
+            for (field, fallbacks) in [
+                (
+                    "modelTranscriptionFallbacks",
+                    &profile.model_transcription_fallbacks,
+                ),
+                ("modelPromptFallbacks", &profile.model_prompt_fallbacks),
+            ] {
+                validate_model_fallbacks(fallbacks)
+                    .map_err(|err| format!("Invalid \"{field}\" in profile \"{name}\": {err}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The secrets stored in the vault, by their setting key, which is also their vault entry.
+    fn secrets_mut(&mut self) -> [(&'static str, &mut Option<String>); 2] {
+        [
+            (secrets::ZOOM_CLIENT_SECRET, &mut self.zoom_client_secret),
+            (secrets::INFERENCE_API_KEY, &mut self.inference_api_key),
+        ]
+    }
+
+    /// Removes the plaintext secrets older settings kept in `settings.json`, as
+    /// `(vault entry, value)` pairs.
+    pub(crate) fn take_plaintext_secrets(&mut self) -> Vec<(&'static str, String)> {
+        self.secrets_mut()
+            .into_iter()
+            .filter_map(|(entry, value)| Some((entry, trimmed(value.take())?)))
+            .collect()
+    }
+
+    /// A copy to hand to the frontend, with secrets masked and leftover plaintext keys dropped.
+    pub(crate) fn masked(&self) -> Self {
+        let mut settings = self.clone();
+        for (_, secret) in settings.secrets_mut() {
+            if secret.is_some() {
+                *secret = Some(layers::MASK.to_string());
+            }
+        }
+        for profile in settings.profiles.values_mut() {
+            profile.nanobanana_api_key = None;
+        }
+        settings
+    }
+
+    fn validate_hub(&self) -> Result<(), String> {
+        self.hub_endpoints()?;
+        self.hub_heartbeat_interval()?;
+        Ok(())
+    }
+
+    /// Resolves Hub endpoints from settings, falling back to the built-in defaults.
+    pub(crate) fn hub_endpoints(&self) -> Result<hub::HubEndpoints, String> {
+        let base_url = self
+            .hub_url
+            .as_deref()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| (value, "settings \"hubUrl\""));
+
+        hub::HubEndpoints::resolve(
+            base_url,
+            self.hub_register_path.as_deref(),
+            self.hub_unregister_path.as_deref(),
+            self.hub_heartbeat_path.as_deref(),
+            self.hub_release_identity_path.as_deref(),
+        )
+    }
+
+    /// The Hub expires registrations after five minutes, so the interval must stay well below that.
+    pub(crate) fn hub_heartbeat_interval(&self) -> Result<Duration, String> {
+        let secs = setting_in_range(
+            "hubHeartbeatIntervalSecs",
+            self.hub_heartbeat_interval_secs,
+            DEFAULT_HUB_HEARTBEAT_INTERVAL_SECS,
+            &HUB_HEARTBEAT_INTERVAL_RANGE_SECS,
+        )?;
+        Ok(Duration::from_secs(secs))
+    }
+
+    pub(crate) fn inference(&self) -> Result<InferenceConfig, String> {
+        let backend = self.inference_backend.unwrap_or_default();
+        let (base_url, field) = match (backend, trimmed(self.inference_base_url.clone())) {
+            (InferenceBackend::Dmr, None) => (self.dmr_base_url.as_deref(), "dmrBaseUrl"),
+            _ => (self.inference_base_url.as_deref(), "inferenceBaseUrl"),
+        };
+        let base_url =
+            validate_service_url(base_url, field, backend.default_base_url().unwrap_or(""))?;
+        if base_url.is_empty() {
+            return Err(format!(
+                "Set \"inferenceBaseUrl\" to use {}.",
+                backend.name()
+            ));
+        }
+        Ok(InferenceConfig {
+            backend,
+            base_url,
+            api_key: trimmed(self.inference_api_key.clone()),
+            warmup_attempts: setting_in_range(
+                "dmrWarmupAttempts",
+                self.dmr_warmup_attempts,
+                DEFAULT_DMR_WARMUP_ATTEMPTS,
+                &DMR_WARMUP_ATTEMPTS_RANGE,
+            )? as usize,
+            model_poll_attempts: setting_in_range(
+                "dmrModelPollAttempts",
+                self.dmr_model_poll_attempts,
+                DEFAULT_DMR_MODEL_POLL_ATTEMPTS,
+                &DMR_MODEL_POLL_ATTEMPTS_RANGE,
+            )? as usize,
+            model_poll_delay: Duration::from_millis(setting_in_range(
+                "dmrModelPollDelayMs",
+                self.dmr_model_poll_delay_ms,
+                DEFAULT_DMR_MODEL_POLL_DELAY_MS,
+                &DMR_MODEL_POLL_DELAY_RANGE_MS,
+            )?),
+            pull_stall_timeout: Duration::from_secs(setting_in_range(
+                "dmrPullStallTimeoutSecs",
+                self.dmr_pull_stall_timeout_secs,
+                DEFAULT_DMR_PULL_STALL_TIMEOUT_SECS,
+                &DMR_PULL_STALL_TIMEOUT_RANGE_SECS,
+            )?),
+            download_budget: match self.model_download_budget_mb {
+                Some(megabytes) => {
+                    validate_in_range(megabytes, &MODEL_DOWNLOAD_BUDGET_RANGE_MB).map_err(
+                        |err| format!("Invalid \"modelDownloadBudgetMb\" in settings: {err}"),
+                    )?;
+                    Some(megabytes * 1024 * 1024)
+                }
+                None => None,
+            },
+        })
+    }
+
+    pub(crate) fn nanobanana(&self) -> Result<NanobananaConfig, String> {
+        let model = self
+            .nanobanana_model
+            .as_deref()
+            .unwrap_or(DEFAULT_NANOBANANA_MODEL);
+        validate_nanobanana_model(model)
+            .map_err(|err| format!("Invalid \"nanobananaModel\" in settings: {err}"))?;
+        let aspect_ratio = self
+            .nanobanana_aspect_ratio
+            .as_deref()
+            .unwrap_or(DEFAULT_NANOBANANA_ASPECT_RATIO);
+        validate_aspect_ratio(aspect_ratio)
+            .map_err(|err| format!("Invalid \"nanobananaAspectRatio\" in settings: {err}"))?;
+        Ok(NanobananaConfig {
+            model: model.to_string(),
+            endpoint: validate_service_url(
+                self.nanobanana_endpoint.as_deref(),
+                "nanobananaEndpoint",
+                DEFAULT_NANOBANANA_ENDPOINT,
+            )?,
+            aspect_ratio: aspect_ratio.to_string(),
+        })
+    }
+
+    /// How long `/background/latest?wait=true` holds a request open before answering.
+    pub(crate) fn background_long_poll_timeout(&self) -> Result<Duration, String> {
+        let secs = setting_in_range(
+            "backgroundLongPollTimeoutSecs",
+            self.background_long_poll_timeout_secs,
+            DEFAULT_BACKGROUND_LONG_POLL_TIMEOUT_SECS,
+            &BACKGROUND_LONG_POLL_TIMEOUT_RANGE_SECS,
+        )?;
+        Ok(Duration::from_secs(secs))
+    }
+
+    pub(crate) fn zoom_oauth(&self) -> Result<zoom_auth::ZoomOAuthConfig, String> {
+        zoom_auth::ZoomOAuthConfig::resolve(
+            self.zoom_client_id.as_deref(),
+            self.zoom_client_secret.as_deref(),
+            self.zoom_authorize_url.as_deref(),
+            self.zoom_token_url.as_deref(),
+            self.zoom_user_url.as_deref(),
+        )
+    }
+
+    pub(crate) fn cloudflared_image(&self) -> docker::ContainerImage {
+        self.cloudflared_image
+            .clone()
+            .unwrap_or_else(docker::ContainerImage::cloudflared)
+    }
+}
+
+fn validate_background_style(style: &str) -> Result<(), String> {
+    if style.chars().count() > BACKGROUND_STYLE_MAX_CHARS || style.contains(char::is_control) {
+        return Err(format!(
+            "Use at most {BACKGROUND_STYLE_MAX_CHARS} characters on one line."
+        ));
+    }
+    Ok(())
+}
+
+fn validate_model_fallbacks(fallbacks: &[String]) -> Result<(), String> {
+    if fallbacks.len() > MAX_MODEL_FALLBACKS {
+        return Err(format!(
+            "List at most {MAX_MODEL_FALLBACKS} fallback models."
+        ));
+    }
+    if fallbacks.iter().any(|model| model.trim().is_empty()) {
+        return Err("Fallback model ids cannot be empty.".to_string());
+    }
+    Ok(())
+}
+
+/// Trims the ids and drops blank entries.
+fn normalize_model_fallbacks(fallbacks: Vec<String>) -> Vec<String> {
+    fallbacks
+        .into_iter()
+        .filter_map(|model| trimmed(Some(model)))
+        .collect()
+}
+
+fn validate_in_range<T: PartialOrd + Display>(
+    value: T,
+    range: &RangeInclusive<T>,
+) -> Result<(), String> {
+    if !range.contains(&value) {
+        return Err(format!(
+            "{value} must be between {} and {}.",
+            range.start(),
+            range.end()
+        ));
+    }
+    Ok(())
+}
+
+/// Resolves an optional number from the settings file against its default and range.
+fn setting_in_range(
+    field: &str,
+    value: Option<u64>,
+    default: u64,
+    range: &RangeInclusive<u64>,
+) -> Result<u64, String> {
+    let value = value.unwrap_or(default);
+    validate_in_range(value, range)
+        .map_err(|err| format!("Invalid \"{field}\" in settings: {err}"))?;
+    Ok(value)
+}
+
+/// Accepts an http(s) URL with a host and returns it without a trailing slash.
+fn validate_service_url(raw: Option<&str>, field: &str, default: &str) -> Result<String, String> {
+    let Some(raw) = raw.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(default.to_string());
+    };
+    match url::Url::parse(raw) {
+        Ok(parsed)
+            if matches!(parsed.scheme(), "http" | "https")
+                && parsed.host_str().is_some()
+                && parsed.query().is_none()
+                && parsed.fragment().is_none() =>
+        {
+            Ok(raw.trim_end_matches('/').to_string())
+        }
+        _ => Err(format!(
+            "Invalid \"{field}\" in settings: \"{raw}\" must be an http:// or https:// URL without a query string."
+        )),
+    }
+}
+
+fn validate_nanobanana_model(model: &str) -> Result<(), String> {
+    let valid = !model.is_empty()
+        && model
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '.' | '_'));
+    if !valid {
+        return Err(format!(
+            "\"{model}\" is not a model id such as \"{DEFAULT_NANOBANANA_MODEL}\"."
+        ));
+    }
+    Ok(())
+}
+
+fn validate_aspect_ratio(ratio: &str) -> Result<(), String> {
+    if !NANOBANANA_ASPECT_RATIOS.contains(&ratio) {
+        return Err(format!(
+            "\"{ratio}\" is not supported. Use one of {}.",
+            NANOBANANA_ASPECT_RATIOS.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Distinguishes a field set to `null` (`Some(None)`) from one left out of the patch (`None`).
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// A partial settings change from the UI. Fields left out are unchanged and `null` resets a
+/// field to its default. Profile fields apply to the active profile.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SettingsPatch {
+    #[serde(default, deserialize_with = "present")]
+    model_transcription: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    model_prompt: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    model_transcription_fallbacks: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "present")]
+    model_prompt_fallbacks: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "present")]
+    background_style: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    nanobanana_key_source: Option<Option<NanobananaKeySource>>,
+    #[serde(default, deserialize_with = "present")]
+    cloudflared_image: Option<Option<docker::ContainerImage>>,
+    #[serde(default, deserialize_with = "present")]
+    hub_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    hub_register_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    hub_unregister_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    hub_heartbeat_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    hub_release_identity_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    hub_heartbeat_interval_secs: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "present")]
+    zoom_client_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    zoom_client_secret: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    zoom_authorize_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    zoom_token_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    zoom_user_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    inference_backend: Option<Option<InferenceBackend>>,
+    #[serde(default, deserialize_with = "present")]
+    inference_base_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    inference_api_key: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    dmr_base_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    dmr_warmup_attempts: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "present")]
+    dmr_model_poll_attempts: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "present")]
+    dmr_model_poll_delay_ms: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "present")]
+    dmr_pull_stall_timeout_secs: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "present")]
+    model_download_budget_mb: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "present")]
+    nanobanana_model: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    nanobanana_endpoint: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    nanobanana_aspect_ratio: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    background_long_poll_timeout_secs: Option<Option<u64>>,
+}
+
+impl SettingsPatch {
+    /// Removes the secrets from the patch, as `(vault entry, value)` pairs where `None`
+    /// clears the entry. They are stored in the vault rather than applied to settings.
+    pub(crate) fn take_secrets(&mut self) -> Vec<(&'static str, Option<String>)> {
+        [
+            (secrets::ZOOM_CLIENT_SECRET, self.zoom_client_secret.take()),
+            (secrets::INFERENCE_API_KEY, self.inference_api_key.take()),
+        ]
+        .into_iter()
+        .filter_map(|(entry, value)| {
+            let value = trimmed(value?);
+            // The mask echoed back from `get_settings` leaves the secret as it is.
+            (value.as_deref() != Some(layers::MASK)).then_some((entry, value))
+        })
+        .collect()
+    }
+
+    /// Model ids the patch sets, keyed by field, so the caller can check them against DMR.
+    pub(crate) fn model_ids(&self) -> Vec<(&'static str, String)> {
+        [
+            ("modelTranscription", &self.model_transcription),
+            ("modelPrompt", &self.model_prompt),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| {
+            let id = trimmed(value.clone()?)?;
+            Some((field, id))
+        })
+        .collect()
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FieldError {
+    pub(crate) field: &'static str,
+    pub(crate) message: String,
+}
+
+impl FieldError {
+    pub(crate) fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// Returned by `update_settings`; `field_errors` is empty when saving itself failed.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SettingsUpdateError {
+    message: String,
+    field_errors: Vec<FieldError>,
+}
+
+impl SettingsUpdateError {
+    pub(crate) fn invalid(field_errors: Vec<FieldError>) -> Self {
+        Self {
+            message: "Some settings are invalid; nothing was saved.".to_string(),
+            field_errors,
+        }
+    }
+}
+
+impl From<String> for SettingsUpdateError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            field_errors: Vec::new(),
+        }
+    }
+}
+
+fn trimmed(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+impl Settings {
+    /// Applies every valid field of `patch` and reports the rest. Callers should only save
+    /// when no errors come back.
+    pub(crate) fn apply_patch(&mut self, patch: SettingsPatch) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        let profile = self.active_profile_mut();
+        // An empty model goes back to the default, which is left unset so the other layers
+        // and future defaults still apply.
+        if let Some(value) = patch.model_transcription {
+            profile.model_transcription = trimmed(value);
+        }
+        if let Some(value) = patch.model_prompt {
+            profile.model_prompt = trimmed(value);
+        }
+        for (field, target, value) in [
+            (
+                "modelTranscriptionFallbacks",
+                &mut profile.model_transcription_fallbacks,
+                patch.model_transcription_fallbacks,
+            ),
+            (
+                "modelPromptFallbacks",
+                &mut profile.model_prompt_fallbacks,
+                patch.model_prompt_fallbacks,
+            ),
+        ] {
+            let Some(value) = value else { continue };
+            let fallbacks = normalize_model_fallbacks(value.unwrap_or_default());
+            match validate_model_fallbacks(&fallbacks) {
+                Ok(()) => *target = fallbacks,
+                Err(err) => errors.push(FieldError::new(field, err)),
+            }
+        }
+        if let Some(value) = patch.background_style {
+            let style = trimmed(value);
+            match style.as_deref().map(validate_background_style) {
+                Some(Err(err)) => errors.push(FieldError::new("backgroundStyle", err)),
+                _ => profile.background_style = style,
+            }
+        }
+        if let Some(value) = patch.nanobanana_key_source {
+            profile.nanobanana_key_source = value.unwrap_or_default();
+        }
+
+        if let Some(value) = patch.cloudflared_image {
+            match value {
+                Some(image) if image.name.trim().is_empty() || image.tag.trim().is_empty() => {
+                    errors.push(FieldError::new(
+                        "cloudflaredImage",
+                        "The image needs both a name and a tag.",
+                    ));
+                }
+                image => self.cloudflared_image = image,
+            }
+        }
+
+        if let Some(value) = patch.hub_url {
+            let value = trimmed(value);
+            match value
+                .as_deref()
+                .map(|url| hub::validate_base_url(url, "settings \"hubUrl\""))
+            {
+                Some(Err(err)) => errors.push(FieldError::new("hubUrl", err)),
+                _ => self.hub_url = value,
+            }
+        }
+        for (field, target, value, default) in [
+            (
+                "hubRegisterPath",
+                &mut self.hub_register_path,
+                patch.hub_register_path,
+                hub::DEFAULT_HUB_REGISTER_PATH,
+            ),
+            (
+                "hubUnregisterPath",
+                &mut self.hub_unregister_path,
+                patch.hub_unregister_path,
+                hub::DEFAULT_HUB_UNREGISTER_PATH,
+            ),
+            (
+                "hubHeartbeatPath",
+                &mut self.hub_heartbeat_path,
+                patch.hub_heartbeat_path,
+                hub::DEFAULT_HUB_HEARTBEAT_PATH,
+            ),
+            (
+                "hubReleaseIdentityPath",
+                &mut self.hub_release_identity_path,
+                patch.hub_release_identity_path,
+                hub::DEFAULT_HUB_RELEASE_IDENTITY_PATH,
+            ),
+        ] {
+            let Some(value) = value else { continue };
+            let value = trimmed(value);
+            match hub::validate_path(value.as_deref(), field, default) {
+                Ok(_) => *target = value,
+                Err(err) => errors.push(FieldError::new(field, err)),
+            }
+        }
+        for (field, target, value, range) in [
+            (
+                "hubHeartbeatIntervalSecs",
+                &mut self.hub_heartbeat_interval_secs,
+                patch.hub_heartbeat_interval_secs,
+                HUB_HEARTBEAT_INTERVAL_RANGE_SECS,
+            ),
+            (
+                "dmrWarmupAttempts",
+                &mut self.dmr_warmup_attempts,
+                patch.dmr_warmup_attempts,
+                DMR_WARMUP_ATTEMPTS_RANGE,
+            ),
+            (
+                "dmrModelPollAttempts",
+                &mut self.dmr_model_poll_attempts,
+                patch.dmr_model_poll_attempts,
+                DMR_MODEL_POLL_ATTEMPTS_RANGE,
+            ),
+            (
+                "dmrModelPollDelayMs",
+                &mut self.dmr_model_poll_delay_ms,
+                patch.dmr_model_poll_delay_ms,
+                DMR_MODEL_POLL_DELAY_RANGE_MS,
+            ),
+            (
+                "dmrPullStallTimeoutSecs",
+                &mut self.dmr_pull_stall_timeout_secs,
+                patch.dmr_pull_stall_timeout_secs,
+                DMR_PULL_STALL_TIMEOUT_RANGE_SECS,
+            ),
+            (
+                "modelDownloadBudgetMb",
+                &mut self.model_download_budget_mb,
+                patch.model_download_budget_mb,
+                MODEL_DOWNLOAD_BUDGET_RANGE_MB,
+            ),
+            (
+                "backgroundLongPollTimeoutSecs",
+                &mut self.background_long_poll_timeout_secs,
+                patch.background_long_poll_timeout_secs,
+                BACKGROUND_LONG_POLL_TIMEOUT_RANGE_SECS,
+            ),
+        ] {
+            let Some(value) = value else { continue };
+            match value.map(|value| validate_in_range(value, &range)) {
+                Some(Err(err)) => errors.push(FieldError::new(field, err)),
+                _ => *target = value,
+            }
+        }
+
+        if let Some(value) = patch.zoom_client_id {
+            self.zoom_client_id = trimmed(value);
+        }
+        for (field, target, value) in [
+            (
+                "zoomAuthorizeUrl",
+                &mut self.zoom_authorize_url,
+                patch.zoom_authorize_url,
+            ),
+            (
+                "zoomTokenUrl",
+                &mut self.zoom_token_url,
+                patch.zoom_token_url,
+            ),
+            ("zoomUserUrl", &mut self.zoom_user_url, patch.zoom_user_url),
+        ] {
+            let Some(value) = value else { continue };
+            let value = trimmed(value);
+            match zoom_auth::validate_url(value.as_deref(), field, "") {
+                Ok(_) => *target = value,
+                Err(err) => errors.push(FieldError::new(field, err)),
+            }
+        }
+
+        let changes_backend = patch.inference_backend.is_some()
+            || patch.inference_base_url.is_some()
+            || patch.dmr_base_url.is_some();
+        if let Some(value) = patch.inference_backend {
+            self.inference_backend = value;
+        }
+        for (field, target, value) in [
+            (
+                "inferenceBaseUrl",
+                &mut self.inference_base_url,
+                patch.inference_base_url,
+            ),
+            ("dmrBaseUrl", &mut self.dmr_base_url, patch.dmr_base_url),
+            (
+                "nanobananaEndpoint",
+                &mut self.nanobanana_endpoint,
+                patch.nanobanana_endpoint,
+            ),
+        ] {
+            let Some(value) = value else { continue };
+            let value = trimmed(value);
+            match validate_service_url(value.as_deref(), field, "") {
+                Ok(_) => *target = value,
+                Err(err) => errors.push(FieldError::new(field, err)),
+            }
+        }
+        if let Some(value) = patch.nanobanana_model {
+            let value = trimmed(value);
+            match value.as_deref().map(validate_nanobanana_model) {
+                Some(Err(err)) => errors.push(FieldError::new("nanobananaModel", err)),
+                _ => self.nanobanana_model = value,
+            }
+        }
+        if let Some(value) = patch.nanobanana_aspect_ratio {
+            let value = trimmed(value);
+            match value.as_deref().map(validate_aspect_ratio) {
+                Some(Err(err)) => errors.push(FieldError::new("nanobananaAspectRatio", err)),
+                _ => self.nanobanana_aspect_ratio = value,
+            }
+        }
+        if changes_backend && errors.is_empty() {
+            if let Err(err) = self.inference() {
+                errors.push(FieldError::new("inferenceBaseUrl", err));
+            }
+        }
+
+        errors
+    }
+}
+
+pub(crate) fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let mut dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|err| format!("Unable to resolve config directory: {err}"))?;
+
+    fs::create_dir_all(&dir).map_err(|err| format!("Unable to create config directory: {err}"))?;
+
+    dir.push("settings.json");
+    Ok(dir)
+}
+
+fn legacy_wheel_state_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let mut dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|err| format!("Unable to resolve config directory: {err}"))?;
+
+    fs::create_dir_all(&dir).map_err(|err| format!("Unable to create config directory: {err}"))?;
+
+    dir.push("wheel_state.json");
+    Ok(dir)
+}
+
+/// The last valid settings. Commands read this snapshot instead of the file, so an
+/// invalid edit on disk never replaces a working configuration.
+#[derive(Default)]
+pub(crate) struct SettingsStore {
+    current: RwLock<Option<Snapshot>>,
+    // Held for every read-modify-write of the file so concurrent commands cannot
+    // overwrite each other's changes.
+    io: Mutex<()>,
+}
+
+#[derive(Clone)]
+struct Snapshot {
+    /// What `settings.json` holds, and all that is ever written back to it.
+    user: Arc<Settings>,
+    /// `user` combined with the other configuration layers. Commands run with these.
+    effective: Arc<Settings>,
+    warnings: Vec<String>,
+}
+
+impl SettingsStore {
+    fn lock_io(&self) -> MutexGuard<'_, ()> {
+        self.io.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get(&self) -> Option<Snapshot> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The effective settings, if they have been loaded.
+    pub(crate) fn snapshot(&self) -> Option<Arc<Settings>> {
+        self.get().map(|snapshot| snapshot.effective)
+    }
+
+    /// Returns the previous snapshot.
+    fn replace(&self, snapshot: Snapshot) -> Option<Snapshot> {
+        self.current
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(snapshot)
+    }
+}
+
+/// The effective settings: `settings.json` combined with the system-wide file, environment,
+/// and command-line flags.
+pub(crate) fn load_settings(app: &AppHandle) -> Result<Settings, String> {
+    Ok(Settings::clone(&loaded(app)?.effective))
+}
+
+/// Only what `settings.json` holds, for exporting and importing it.
+pub(crate) fn load_user_settings(app: &AppHandle) -> Result<Settings, String> {
+    Ok(Settings::clone(&loaded(app)?.user))
+}
+
+/// Every effective setting and the layer that supplied it.
+pub(crate) fn describe(app: &AppHandle) -> Result<ConfigReport, String> {
+    let mut user = load_user_settings(app)?;
+    let (layered, _) = with_vault_secrets(app, &user);
+    let (effective, mut report) = layers::apply(&layered);
+    for (key, value) in user.secrets_mut() {
+        if value.is_none() {
+            report.credit_vault(key);
+        }
+    }
+    let backend = effective.inference_backend.unwrap_or_default();
+    for (key, role) in [
+        ("modelTranscription", ModelRole::Transcription),
+        ("modelPrompt", ModelRole::Prompt),
+    ] {
+        if let Some(model) = backend.default_model(role) {
+            report.fill_default(key, model.into());
+        }
+    }
+    Ok(report)
+}
+
+fn loaded(app: &AppHandle) -> Result<Snapshot, String> {
+    let store = &app.state::<Arc<AppState>>().settings;
+    if let Some(snapshot) = store.get() {
+        return Ok(snapshot);
+    }
+    let _io = store.lock_io();
+    current_settings(app)
+}
+
+/// The snapshot, or the file on first use. Callers hold the I/O lock.
+fn current_settings(app: &AppHandle) -> Result<Snapshot, String> {
+    let store = &app.state::<Arc<AppState>>().settings;
+    if let Some(snapshot) = store.get() {
+        return Ok(snapshot);
+    }
+    publish(app, read_or_recover(app)?);
+    store
+        .get()
+        .ok_or_else(|| "Settings were not loaded.".to_string())
+}
+
+/// Applies `change` to the user's settings and saves the result, holding the I/O lock
+/// throughout. Nothing is saved when `change` fails. Use `load_settings` afterwards for
+/// the effective settings.
+pub(crate) fn update<T, E: From<String>>(
+    app: &AppHandle,
+    change: impl FnOnce(&mut Settings) -> Result<T, E>,
+) -> Result<T, E> {
+    let store = &app.state::<Arc<AppState>>().settings;
+    let _io = store.lock_io();
+    let mut settings = Settings::clone(&current_settings(app)?.user);
+    let result = change(&mut settings)?;
+    // Publish before writing so the watcher sees the new file as already current.
+    publish(app, settings.clone());
+    write_settings(&settings_path(app)?, &settings)?;
+    Ok(result)
+}
+
+/// Layers the current settings again, after the vault secrets they draw on changed.
+pub(crate) fn republish(app: &AppHandle) -> Result<(), String> {
+    let store = &app.state::<Arc<AppState>>().settings;
+    let _io = store.lock_io();
+    let user = Settings::clone(&current_settings(app)?.user);
+    publish(app, user);
+    Ok(())
+}
+
+/// `user` with the secrets kept in the vault, which stand in for `settings.json` in the
+/// layers. Returns why the vault could not be read, if it could not.
+fn with_vault_secrets(app: &AppHandle, user: &Settings) -> (Settings, Option<String>) {
+    let vault = &app.state::<Arc<AppState>>().secrets;
+    let mut settings = user.clone();
+    let mut warning = None;
+    for (entry, value) in settings.secrets_mut() {
+        // A plaintext value still waiting for the startup import.
+        if value.is_some() {
+            continue;
+        }
+        match vault.get(app, entry) {
+            Ok(secret) => *value = secret,
+            Err(err) => {
+                warning = Some(format!(
+                    "The Zoom client secret and inference API key are unavailable: {err}"
+                ));
+                break;
+            }
+        }
+    }
+    (settings, warning)
+}
+
+/// Layers `user` into the current snapshot and tells the frontend when the effective
+/// settings changed. Returns whether they did.
+fn publish(app: &AppHandle, user: Settings) -> bool {
+    let store = &app.state::<Arc<AppState>>().settings;
+    let (layered, vault_warning) = with_vault_secrets(app, &user);
+    let (effective, report) = layers::apply(&layered);
+    let mut warnings = report.warnings().to_vec();
+    warnings.extend(vault_warning);
+    if let Err(err) = effective.validate() {
+        warnings.push(format!(
+            "Features that use this setting will fail until settings.json is fixed: {err}"
+        ));
+    }
+    if let Err(err) = effective.validate_hub() {
+        warnings.push(format!("The agent cannot start until this is fixed: {err}"));
+    }
+    let previous = store.replace(Snapshot {
+        user: Arc::new(user),
+        effective: Arc::new(effective.clone()),
+        warnings: warnings.clone(),
+    });
+
+    let previous_warnings = previous
+        .as_ref()
+        .map(|snapshot| snapshot.warnings.as_slice())
+        .unwrap_or_default();
+    for warning in warnings
+        .iter()
+        .filter(|warning| !previous_warnings.contains(warning))
+    {
+        let message = format!("[Settings] {warning}");
+        eprintln!("{message}");
+        emit_log(app, LogLevel::Error, &message);
+    }
+
+    if previous.is_some_and(|snapshot| *snapshot.effective == effective) {
+        return false;
+    }
+    if let Err(err) = app.emit(SETTINGS_CHANGED_EVENT, effective.masked()) {
+        eprintln!("[Settings] Failed to emit settings change: {err}");
+    }
+    true
+}
+
+/// Watches `settings.json` and republishes it whenever it changes on disk.
+pub(crate) fn watch(app: AppHandle) -> Result<(), String> {
+    let path = settings_path(&app)?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| "Settings file has no parent directory.".to_string())?
+        .to_path_buf();
+    let file_name = path.file_name().map(ToOwned::to_owned);
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    // Watch the directory rather than the file: editors that save by renaming a temporary
+    // file over `settings.json` would otherwise detach the watch.
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event)
+                if !event.kind.is_access()
+                    && event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == file_name.as_deref()) =>
+            {
+                let _ = sender.send(());
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("[Settings] File watcher error: {err}"),
+        })
+        .map_err(|err| format!("Failed to create settings watcher: {err}"))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|err| format!("Failed to watch \"{}\": {err}", dir.display()))?;
+    println!("[Settings] Watching {} for changes.", path.display());
+
+    tauri::async_runtime::spawn(async move {
+        let _watcher = watcher;
+        while receiver.recv().await.is_some() {
+            sleep(RELOAD_DEBOUNCE).await;
+            while receiver.try_recv().is_ok() {}
+            reload(&app);
+        }
+    });
+    Ok(())
+}
+
+fn reload(app: &AppHandle) {
+    let state = app.state::<Arc<AppState>>();
+    let _io = state.settings.lock_io();
+    match read_settings(app).and_then(|settings| settings.validate().map(|()| settings)) {
+        Ok(settings) => {
+            if publish(app, settings) {
+                println!("[Settings] Reloaded settings from disk.");
+                emit_log(app, LogLevel::Info, "[Settings] Reloaded settings.json.");
+            }
+        }
+        Err(err) => {
+            let message = format!(
+                "[Settings] Ignoring the edit to settings.json and keeping the last valid settings: {err}"
+            );
+            eprintln!("{message}");
+            emit_log(app, LogLevel::Error, &message);
+        }
+    }
+}
+
+/// Parses and migrates a settings document, returning it with the schema version it had.
+fn parse_settings(contents: &str) -> Result<(Settings, u32), String> {
+    let document: Value =
+        serde_json::from_str(contents).map_err(|err| format!("Failed to parse settings: {err}"))?;
+    let from_version = schema_version(&document)?;
+    Ok((migrate(document)?, from_version))
+}
+
+/// Reads the settings file at startup. If it cannot be parsed, the newest backup that parses
+/// takes its place and the broken file is set aside for inspection. A file that parses but
+/// fails validation is kept; `publish` reports what is wrong with it.
+fn read_or_recover(app: &AppHandle) -> Result<Settings, String> {
+    let err = match read_settings(app) {
+        Ok(settings) => return Ok(settings),
+        Err(err) => err,
+    };
+    let path = settings_path(app)?;
+    // A file from a newer app is not corrupt; leave it for that version. Neither is one that
+    // could not be read or saved.
+    let corrupt = fs::read_to_string(&path).is_ok_and(|contents| {
+        match serde_json::from_str::<Value>(&contents) {
+            Ok(document) => schema_version(&document).is_ok() && parse_settings(&contents).is_err(),
+            Err(_) => true,
+        }
+    });
+    if !corrupt {
+        return Err(err);
+    }
+
+    for index in 1..=SETTINGS_BACKUP_COUNT {
+        let backup = settings_backup_path(&path, index);
+        let Ok(contents) = fs::read_to_string(&backup) else {
+            continue;
+        };
+        if parse_settings(&contents).is_err() {
+            continue;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let corrupt = path.with_file_name(format!("settings.corrupt-{timestamp}.json"));
+        fs::rename(&path, &corrupt)
+            .map_err(|rename_err| format!("{err} (could not set the file aside: {rename_err})"))?;
+        fs::copy(&backup, &path).map_err(|copy_err| {
+            format!(
+                "{err} (could not restore \"{}\": {copy_err})",
+                backup.display()
+            )
+        })?;
+        let settings = read_settings(app)?;
+
+        let message = format!(
+            "[Settings] settings.json could not be loaded ({err}). Restored \"{}\"; the broken file was kept as \"{}\".",
+            backup.display(),
+            corrupt.display()
+        );
+        eprintln!("{message}");
+        emit_log(app, LogLevel::Error, &message);
+        if let Err(emit_err) = app.emit(SETTINGS_RECOVERED_EVENT, &message) {
+            eprintln!("[Settings] Failed to emit settings recovery: {emit_err}");
+        }
+        return Ok(settings);
+    }
+
+    Err(err)
+}
+
+fn read_settings(app: &AppHandle) -> Result<Settings, String> {
+    let path = settings_path(app)?;
+    let (mut settings, mut needs_save) = match fs::read_to_string(&path) {
+        Ok(contents) => {
+            let (settings, from_version) = parse_settings(&contents)?;
+            if from_version < SETTINGS_SCHEMA_VERSION {
+                let backup = backup_path(&path, from_version);
+                copy_without_plaintext_keys(&path, &backup).map_err(|err| {
+                    format!(
+                        "Failed to back up settings to \"{}\" before migrating: {err}",
+                        backup.display()
+                    )
+                })?;
+                println!(
+                    "[Settings] Migrating settings from schema {from_version} to {SETTINGS_SCHEMA_VERSION} (backup at {}).",
+                    backup.display()
+                );
+            }
+            (settings, from_version < SETTINGS_SCHEMA_VERSION)
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            let settings = if let Some(document) = read_legacy_wheel_state(app)? {
+                let settings = migrate(document)?;
+                if let Ok(legacy_path) = legacy_wheel_state_path(app) {
+                    let _ = fs::remove_file(legacy_path);
+                }
+                settings
+            } else {
+                Settings {
+                    schema_version: SETTINGS_SCHEMA_VERSION,
+                    ..Default::default()
+                }
+            };
+            (settings, true)
+        }
+        Err(err) => return Err(format!("Failed to read settings: {err}")),
+    };
+
+    needs_save |= settings.ensure_profiles();
+    if needs_save {
+        write_settings(&path, &settings)?;
+    }
+
+    Ok(settings)
+}
+
+fn settings_backup_path(path: &Path, index: usize) -> PathBuf {
+    path.with_file_name(format!("settings.backup.{index}.json"))
+}
+
+/// Shifts `settings.backup.<n>.json` along and copies the current file into slot 1, as
+/// long as the current file still parses.
+fn rotate_backups(path: &Path) {
+    let parses = fs::read_to_string(path)
+        .ok()
+        .is_some_and(|contents| serde_json::from_str::<Value>(&contents).is_ok());
+    if !parses {
+        return;
+    }
+    for index in (1..SETTINGS_BACKUP_COUNT).rev() {
+        let _ = fs::rename(
+            settings_backup_path(path, index),
+            settings_backup_path(path, index + 1),
+        );
+    }
+    if let Err(err) = copy_without_plaintext_keys(path, &settings_backup_path(path, 1)) {
+        eprintln!("[Settings] Failed to back up settings: {err}");
+    }
+}
+
+/// Moves plaintext keys left by older versions into the secrets vault, then scrubs them from
+/// the backups and migration copies. Runs once at startup rather than on every load, so a
+/// vault that cannot be opened leaves the keys in place instead of failing settings.
+pub(crate) fn import_plaintext_keys(app: &AppHandle) -> Result<(), String> {
+    update(app, |settings| {
+        secrets::import_plaintext_keys(app, settings)
+    })?;
+    scrub_backups(&settings_path(app)?);
+    Ok(())
+}
+
+/// Removes plaintext keys from a settings document of any schema. Returns whether any were found.
+fn strip_plaintext_keys(document: &mut Value) -> bool {
+    let Some(fields) = document.as_object_mut() else {
+        return false;
+    };
+    let mut stripped = false;
+    for key in [
+        "nanobananaApiKey",
+        secrets::ZOOM_CLIENT_SECRET,
+        secrets::INFERENCE_API_KEY,
+    ] {
+        stripped |= fields.remove(key).is_some();
+    }
+    if let Some(profiles) = fields.get_mut("profiles").and_then(Value::as_object_mut) {
+        for profile in profiles.values_mut().filter_map(Value::as_object_mut) {
+            stripped |= profile.remove("nanobananaApiKey").is_some();
+        }
+    }
+    stripped
+}
+
+/// Copies a settings file, leaving out any plaintext keys it still holds.
+fn copy_without_plaintext_keys(from: &Path, to: &Path) -> std::io::Result<()> {
+    let contents = fs::read_to_string(from)?;
+    if let Ok(mut document) = serde_json::from_str::<Value>(&contents) {
+        if strip_plaintext_keys(&mut document) {
+            return fs::write(to, serde_json::to_string_pretty(&document)?);
+        }
+    }
+    fs::write(to, contents)
+}
+
+/// Rewrites every backup, migration copy, and set-aside file next to `path` that still
+/// holds a plaintext key.
+fn scrub_backups(path: &Path) {
+    let Some(entries) = path.parent().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let copy = entry.path();
+        let is_copy = copy != path
+            && copy.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+                name.starts_with("settings.") && name.ends_with(".json")
+            });
+        if !is_copy {
+            continue;
+        }
+        let Some(mut document) = fs::read_to_string(&copy)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        else {
+            continue;
+        };
+        if !strip_plaintext_keys(&mut document) {
+            continue;
+        }
+        let result = serde_json::to_string_pretty(&document)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| fs::write(&copy, contents).map_err(|err| err.to_string()));
+        match result {
+            Ok(()) => println!(
+                "[Secrets] Removed plaintext keys from \"{}\".",
+                copy.display()
+            ),
+            Err(err) => eprintln!(
+                "[Secrets] Failed to remove plaintext keys from \"{}\": {err}",
+                copy.display()
+            ),
+        }
+    }
+}
+
+/// Writes through a synced temporary file and a rename so a crash leaves either the old
+/// or the new file, never half of one.
+fn write_settings(path: &Path, settings: &Settings) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(settings)
+        .map_err(|err| format!("Failed to serialize settings: {err}"))?;
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+    rotate_backups(path);
+
+    let temp_path = path.with_extension("json.tmp");
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        // Persist the rename itself; directories cannot be synced this way on Windows.
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            let _ = fs::File::open(dir).and_then(|dir| dir.sync_all());
+        }
+        Ok(())
+    };
+    write().map_err(|err| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to write settings: {err}")
+    })
+}
+
+/// Wraps the pre-settings `wheel_state.json` in a schema-0 settings document.
+fn read_legacy_wheel_state(app: &AppHandle) -> Result<Option<Value>, String> {
+    let legacy_path = legacy_wheel_state_path(app)?;
+    match fs::read_to_string(&legacy_path) {
+        Ok(contents) => {
+            let wheels: WheelState = serde_json::from_str(&contents)
+                .map_err(|err| format!("Failed to parse legacy wheel state: {err}"))?;
+            let wheels = serde_json::to_value(wheels)
+                .map_err(|err| format!("Failed to convert legacy wheel state: {err}"))?;
+            Ok(Some(Value::Object(Map::from_iter([(
+                "wheels".to_string(),
+                wheels,
+            )]))))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("Failed to read legacy wheel state: {err}")),
+    }
+}
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// Step `i` upgrades a document from schema `i` to `i + 1`. Steps only ever get appended.
+const MIGRATIONS: [Migration; SETTINGS_SCHEMA_VERSION as usize] = [fold_into_default_profile];
+
+fn schema_version(document: &Value) -> Result<u32, String> {
+    let version = match document.get("schemaVersion") {
+        None | Some(Value::Null) => 0,
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("Invalid \"schemaVersion\" in settings: {value}."))?,
+    };
+    if version > SETTINGS_SCHEMA_VERSION {
+        return Err(format!(
+            "Settings were written by a newer version of the app (schema {version}; this version supports up to {SETTINGS_SCHEMA_VERSION}). Update the app or restore an older settings backup."
+        ));
+    }
+    Ok(version)
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    path.with_file_name(format!("settings.v{version}.backup.json"))
+}
+
+/// Runs every migration after the document's schema version, then parses the result.
+fn migrate(mut document: Value) -> Result<Settings, String> {
+    let from_version = schema_version(&document)?;
+    let fields = document
+        .as_object_mut()
+        .ok_or_else(|| "Failed to parse settings: expected a JSON object.".to_string())?;
+    for (index, step) in MIGRATIONS.iter().enumerate().skip(from_version as usize) {
+        step(fields).map_err(|err| {
+            format!(
+                "Failed to migrate settings from schema {index} to {}: {err}",
+                index + 1
+            )
+        })?;
+    }
+    fields.insert(
+        "schemaVersion".to_string(),
+        Value::from(SETTINGS_SCHEMA_VERSION),
+    );
+    serde_json::from_value(document).map_err(|err| format!("Failed to parse settings: {err}"))
+}
+
+/// 0 → 1: moves the pre-profile top-level fields into a `Default` profile.
+fn fold_into_default_profile(fields: &mut Map<String, Value>) -> Result<(), String> {
+    let mut profile = Map::new();
+    for key in [
+        "wheels",
+        "screenName",
+        "modelTranscription",
+        "modelPrompt",
+        "nanobananaApiKey",
+    ] {
+        if let Some(value) = fields.remove(key) {
+            profile.insert(key.to_string(), value);
+        }
+    }
+
+    let profiles = fields
+        .entry("profiles")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| "\"profiles\" is not an object".to_string())?;
+    if profiles.is_empty() {
+        profiles.insert(DEFAULT_PROFILE_NAME.to_string(), Value::Object(profile));
+        fields.insert(
+            "activeProfile".to_string(),
+            Value::from(DEFAULT_PROFILE_NAME),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{
+        layers::MASK, migrate, parse_settings, SettingsPatch, DEFAULT_PROFILE_NAME,
+        SETTINGS_SCHEMA_VERSION,
+    };
+    use crate::secrets;
+
+    /// A settings file from before profiles, with no `schemaVersion`.
+    fn schema_zero() -> Value {
+        json!({
+            "wheels": { "positions": [2, 0, 1], "activeIndex": 1 },
+            "screenName": "alice",
+            "modelTranscription": "ai/whisper",
+            "modelPrompt": "ai/gemma3",
+            "nanobananaApiKey": "legacy-key",
+            "hubUrl": "https://hub.example"
+        })
+    }
+
+    #[test]
+    fn migrates_schema_zero_to_the_current_schema() {
+        let (settings, from_version) = parse_settings(&schema_zero().to_string()).unwrap();
+        assert_eq!(from_version, 0);
+
+        let document = serde_json::to_value(&settings).unwrap();
+        assert_eq!(document["schemaVersion"], SETTINGS_SCHEMA_VERSION);
+        assert_eq!(document["activeProfile"], DEFAULT_PROFILE_NAME);
+        assert_eq!(document["hubUrl"], "https://hub.example");
+        for key in [
+            "wheels",
+            "screenName",
+            "modelTranscription",
+            "nanobananaApiKey",
+        ] {
+            assert!(document.get(key).is_none(), "{key} left at the top level");
+        }
+
+        let profile = &document["profiles"][DEFAULT_PROFILE_NAME];
+        assert_eq!(
+            profile["wheels"],
+            json!({ "positions": [2, 0, 1], "activeIndex": 1 })
+        );
+        assert_eq!(profile["screenName"], "alice");
+        assert_eq!(profile["modelTranscription"], "ai/whisper");
+        assert_eq!(profile["modelPrompt"], "ai/gemma3");
+        assert_eq!(profile["nanobananaApiKey"], "legacy-key");
+    }
+
+    #[test]
+    fn rejects_settings_from_a_newer_schema() {
+        let document = json!({ "schemaVersion": SETTINGS_SCHEMA_VERSION + 1 });
+        let err = parse_settings(&document.to_string()).err().unwrap();
+        assert!(err.contains("newer version of the app"), "{err}");
+    }
+
+    #[test]
+    fn rejects_a_malformed_schema_version() {
+        let err = migrate(json!({ "schemaVersion": "two" })).err().unwrap();
+        assert!(err.contains("Invalid \"schemaVersion\""), "{err}");
+    }
+
+    #[test]
+    fn migrating_again_changes_nothing() {
+        let once = serde_json::to_value(migrate(schema_zero()).unwrap()).unwrap();
+        let (settings, from_version) = parse_settings(&once.to_string()).unwrap();
+        assert_eq!(from_version, SETTINGS_SCHEMA_VERSION);
+        assert_eq!(serde_json::to_value(settings).unwrap(), once);
+    }
+
+    #[test]
+    fn patch_secrets_skip_the_mask() {
+        let mut patch: SettingsPatch = serde_json::from_value(json!({
+            "zoomClientSecret": MASK,
+            "inferenceApiKey": null
+        }))
+        .unwrap();
+        assert_eq!(patch.take_secrets(), [(secrets::INFERENCE_API_KEY, None)]);
+    }
+}
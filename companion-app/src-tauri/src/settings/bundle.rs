@@ -0,0 +1,632 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    layers::MASK, normalize_model_fallbacks, trimmed, validate_background_style,
+    validate_model_fallbacks, FieldError, InferenceBackend, NanobananaKeySource, Settings,
+    SettingsPatch, SETTINGS_SCHEMA_VERSION,
+};
+use crate::{
+    docker,
+    secrets::{self, SealedSecrets},
+};
+
+const BUNDLE_VERSION: u32 = 1;
+
+/// The shareable part of a profile. Wheels and the screen name belong to one person and
+/// stay on their machine.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model_transcription: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    model_transcription_fallbacks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    model_prompt_fallbacks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background_style: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nanobanana_key_source: Option<NanobananaKeySource>,
+}
+
+/// Machine-wide settings that were set on the exporting machine.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct BundleMachine {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cloudflared_image: Option<docker::ContainerImage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hub_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hub_register_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hub_unregister_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hub_heartbeat_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hub_release_identity_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hub_heartbeat_interval_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zoom_client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zoom_authorize_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zoom_token_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zoom_user_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inference_backend: Option<InferenceBackend>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inference_base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dmr_base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dmr_warmup_attempts: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dmr_model_poll_attempts: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dmr_model_poll_delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dmr_pull_stall_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model_download_budget_mb: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nanobanana_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nanobanana_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nanobanana_aspect_ratio: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background_long_poll_timeout_secs: Option<u64>,
+}
+
+/// The plaintext inside a bundle's sealed secrets.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct BundleSecrets {
+    /// Nano Banana keys by profile name.
+    #[serde(default)]
+    nanobanana_keys: BTreeMap<String, String>,
+    #[serde(default)]
+    shared_nanobanana_key: Option<String>,
+    #[serde(default)]
+    zoom_client_secret: Option<String>,
+    #[serde(default)]
+    inference_api_key: Option<String>,
+}
+
+/// Portable, versioned copy of the settings a team shares, for setting up another machine.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SettingsBundle {
+    bundle_version: u32,
+    schema_version: u32,
+    exported_at_ms: u64,
+    profiles: BTreeMap<String, BundleProfile>,
+    #[serde(default)]
+    machine: BundleMachine,
+    /// Present only when secrets were explicitly exported with a passphrase.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secrets: Option<SealedSecrets>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingChange {
+    path: String,
+    current: Value,
+    incoming: Value,
+}
+
+/// What an import would change, shown to the user before anything is saved.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportPreview {
+    changes: Vec<SettingChange>,
+    /// The secrets the import stores in the vault.
+    secrets: Vec<String>,
+    /// The bundle carries secrets but no passphrase was given, so they are left out.
+    secrets_skipped: bool,
+}
+
+impl ImportPreview {
+    pub(crate) fn change_count(&self) -> usize {
+        self.changes.len() + self.secrets.len()
+    }
+}
+
+/// The result of applying a bundle to the current settings, before anything is saved.
+pub(crate) struct ImportPlan {
+    pub(crate) settings: Settings,
+    /// Vault entries to store, as `(entry, value)`.
+    pub(crate) vault_entries: Vec<(String, String)>,
+    pub(crate) preview: ImportPreview,
+}
+
+impl SettingsBundle {
+    /// Builds a bundle from `settings`. Secrets are only included, sealed with
+    /// `passphrase`, when one is given; `vault` looks up vault entries by name.
+    pub(crate) fn export(
+        settings: &Settings,
+        passphrase: Option<&str>,
+        vault: impl Fn(&str) -> Result<Option<String>, String>,
+    ) -> Result<Self, String> {
+        let profiles = settings
+            .profiles
+            .iter()
+            .map(|(name, profile)| {
+                (
+                    name.clone(),
+                    BundleProfile {
+                        model_transcription: profile.model_transcription.clone(),
+                        model_prompt: profile.model_prompt.clone(),
+                        model_transcription_fallbacks: profile
+                            .model_transcription_fallbacks
+                            .clone(),
+                        model_prompt_fallbacks: profile.model_prompt_fallbacks.clone(),
+                        background_style: profile.background_style.clone(),
+                        nanobanana_key_source: Some(profile.nanobanana_key_source),
+                    },
+                )
+            })
+            .collect();
+
+        let secrets = match passphrase {
+            Some(passphrase) => {
+                let mut nanobanana_keys = BTreeMap::new();
+                for name in settings.profiles.keys() {
+                    if let Some(key) = vault(&secrets::profile_nanobanana_key(name))? {
+                        nanobanana_keys.insert(name.clone(), key);
+                    }
+                }
+                let plaintext = serde_json::to_vec(&BundleSecrets {
+                    nanobanana_keys,
+                    shared_nanobanana_key: vault(secrets::SHARED_NANOBANANA_KEY)?,
+                    zoom_client_secret: vault(secrets::ZOOM_CLIENT_SECRET)?,
+                    inference_api_key: vault(secrets::INFERENCE_API_KEY)?,
+                })
+                .map_err(|err| format!("Failed to serialize secrets: {err}"))?;
+                Some(SealedSecrets::seal(passphrase, &plaintext)?)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            bundle_version: BUNDLE_VERSION,
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            exported_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            profiles,
+            machine: BundleMachine {
+                cloudflared_image: settings.cloudflared_image.clone(),
+                hub_url: settings.hub_url.clone(),
+                hub_register_path: settings.hub_register_path.clone(),
+                hub_unregister_path: settings.hub_unregister_path.clone(),
+                hub_heartbeat_path: settings.hub_heartbeat_path.clone(),
+                hub_release_identity_path: settings.hub_release_identity_path.clone(),
+                hub_heartbeat_interval_secs: settings.hub_heartbeat_interval_secs,
+                zoom_client_id: settings.zoom_client_id.clone(),
+                zoom_authorize_url: settings.zoom_authorize_url.clone(),
+                zoom_token_url: settings.zoom_token_url.clone(),
+                zoom_user_url: settings.zoom_user_url.clone(),
+                inference_backend: settings.inference_backend,
+                inference_base_url: settings.inference_base_url.clone(),
+                dmr_base_url: settings.dmr_base_url.clone(),
+                dmr_warmup_attempts: settings.dmr_warmup_attempts,
+                dmr_model_poll_attempts: settings.dmr_model_poll_attempts,
+                dmr_model_poll_delay_ms: settings.dmr_model_poll_delay_ms,
+                dmr_pull_stall_timeout_secs: settings.dmr_pull_stall_timeout_secs,
+                model_download_budget_mb: settings.model_download_budget_mb,
+                nanobanana_model: settings.nanobanana_model.clone(),
+                nanobanana_endpoint: settings.nanobanana_endpoint.clone(),
+                nanobanana_aspect_ratio: settings.nanobanana_aspect_ratio.clone(),
+                background_long_poll_timeout_secs: settings.background_long_poll_timeout_secs,
+            },
+            secrets,
+        })
+    }
+
+    pub(crate) fn parse(contents: &str) -> Result<Self, String> {
+        let bundle: Self = serde_json::from_str(contents)
+            .map_err(|err| format!("This is not a settings bundle: {err}"))?;
+        if bundle.bundle_version > BUNDLE_VERSION || bundle.schema_version > SETTINGS_SCHEMA_VERSION
+        {
+            return Err(
+                "This bundle was exported by a newer version of the app. Update the app to import it."
+                    .to_string(),
+            );
+        }
+        Ok(bundle)
+    }
+
+    /// Applies the bundle to a copy of `current`. Profiles in the bundle are created or
+    /// updated; profiles and fields it does not mention are left alone.
+    pub(crate) fn plan(
+        &self,
+        current: &Settings,
+        passphrase: Option<&str>,
+    ) -> Result<ImportPlan, String> {
+        let mut settings = current.clone();
+        let machine = &self.machine;
+        let mut errors = settings.apply_patch(SettingsPatch {
+            cloudflared_image: machine.cloudflared_image.clone().map(Some),
+            hub_url: machine.hub_url.clone().map(Some),
+            hub_register_path: machine.hub_register_path.clone().map(Some),
+            hub_unregister_path: machine.hub_unregister_path.clone().map(Some),
+            hub_heartbeat_path: machine.hub_heartbeat_path.clone().map(Some),
+            hub_release_identity_path: machine.hub_release_identity_path.clone().map(Some),
+            hub_heartbeat_interval_secs: machine.hub_heartbeat_interval_secs.map(Some),
+            zoom_client_id: machine.zoom_client_id.clone().map(Some),
+            zoom_authorize_url: machine.zoom_authorize_url.clone().map(Some),
+            zoom_token_url: machine.zoom_token_url.clone().map(Some),
+            zoom_user_url: machine.zoom_user_url.clone().map(Some),
+            inference_backend: machine.inference_backend.map(Some),
+            inference_base_url: machine.inference_base_url.clone().map(Some),
+            dmr_base_url: machine.dmr_base_url.clone().map(Some),
+            dmr_warmup_attempts: machine.dmr_warmup_attempts.map(Some),
+            dmr_model_poll_attempts: machine.dmr_model_poll_attempts.map(Some),
+            dmr_model_poll_delay_ms: machine.dmr_model_poll_delay_ms.map(Some),
+            dmr_pull_stall_timeout_secs: machine.dmr_pull_stall_timeout_secs.map(Some),
+            model_download_budget_mb: machine.model_download_budget_mb.map(Some),
+            nanobanana_model: machine.nanobanana_model.clone().map(Some),
+            nanobanana_endpoint: machine.nanobanana_endpoint.clone().map(Some),
+            nanobanana_aspect_ratio: machine.nanobanana_aspect_ratio.clone().map(Some),
+            background_long_poll_timeout_secs: machine.background_long_poll_timeout_secs.map(Some),
+            ..Default::default()
+        });
+
+        let mut profile_names = BTreeMap::new();
+        for (name, incoming) in &self.profiles {
+            let target = match settings.find_profile(name) {
+                Some(existing) => existing,
+                None => {
+                    if let Err(err) = settings.create_profile(name, None) {
+                        errors.push(FieldError::new("profiles", err));
+                        continue;
+                    }
+                    name.trim().to_string()
+                }
+            };
+            let profile = settings
+                .profiles
+                .get_mut(&target)
+                .expect("profile was found or created above");
+            if let Some(model) = trimmed(incoming.model_transcription.clone()) {
+                profile.model_transcription = Some(model);
+            }
+            if let Some(model) = trimmed(incoming.model_prompt.clone()) {
+                profile.model_prompt = Some(model);
+            }
+            for (field, list, fallbacks) in [
+                (
+                    "modelTranscriptionFallbacks",
+                    &mut profile.model_transcription_fallbacks,
+                    &incoming.model_transcription_fallbacks,
+                ),
+                (
+                    "modelPromptFallbacks",
+                    &mut profile.model_prompt_fallbacks,
+                    &incoming.model_prompt_fallbacks,
+                ),
+            ] {
+                let fallbacks = normalize_model_fallbacks(fallbacks.clone());
+                if fallbacks.is_empty() {
+                    continue;
+                }
+                match validate_model_fallbacks(&fallbacks) {
+                    Ok(()) => *list = fallbacks,
+                    Err(err) => errors.push(FieldError::new(
+                        field,
+                        format!("Profile \"{target}\": {err}"),
+                    )),
+                }
+            }
+            if let Some(style) = trimmed(incoming.background_style.clone()) {
+                match validate_background_style(&style) {
+                    Ok(()) => profile.background_style = Some(style),
+                    Err(err) => errors.push(FieldError::new(
+                        "backgroundStyle",
+                        format!("Profile \"{target}\": {err}"),
+                    )),
+                }
+            }
+            if let Some(source) = incoming.nanobanana_key_source {
+                profile.nanobanana_key_source = source;
+            }
+            profile_names.insert(name.clone(), target);
+        }
+
+        if !errors.is_empty() {
+            let details: Vec<String> = errors
+                .iter()
+                .map(|error| format!("{}: {}", error.field, error.message))
+                .collect();
+            return Err(format!(
+                "The bundle contains invalid settings. {}",
+                details.join(" ")
+            ));
+        }
+
+        let passphrase = passphrase.filter(|value| !value.is_empty());
+        let (bundle_secrets, secrets_skipped) = match (&self.secrets, passphrase) {
+            (Some(sealed), Some(passphrase)) => (
+                serde_json::from_slice::<BundleSecrets>(&sealed.open(passphrase)?)
+                    .map_err(|err| format!("Failed to parse the bundle's secrets: {err}"))?,
+                false,
+            ),
+            (Some(_), None) => (BundleSecrets::default(), true),
+            (None, _) => (BundleSecrets::default(), false),
+        };
+
+        let mut vault_entries = Vec::new();
+        let mut secret_descriptions = Vec::new();
+        for (entry, value, description) in [
+            (
+                secrets::ZOOM_CLIENT_SECRET,
+                bundle_secrets.zoom_client_secret,
+                "Zoom client secret",
+            ),
+            (
+                secrets::INFERENCE_API_KEY,
+                bundle_secrets.inference_api_key,
+                "Inference API key",
+            ),
+        ] {
+            if let Some(value) = unmasked(value) {
+                vault_entries.push((entry.to_string(), value));
+                secret_descriptions.push(description.to_string());
+            }
+        }
+        for (name, key) in bundle_secrets.nanobanana_keys {
+            let Some(key) = unmasked(Some(key)) else {
+                continue;
+            };
+            if let Some(target) = profile_names.get(&name) {
+                vault_entries.push((secrets::profile_nanobanana_key(target), key));
+                secret_descriptions.push(format!("Nano Banana key for profile \"{target}\""));
+            }
+        }
+        if let Some(key) = unmasked(bundle_secrets.shared_nanobanana_key) {
+            vault_entries.push((secrets::SHARED_NANOBANANA_KEY.to_string(), key));
+            secret_descriptions.push("Machine-wide Nano Banana key".to_string());
+        }
+
+        let mut changes = Vec::new();
+        diff(
+            "",
+            &serde_json::to_value(current).unwrap_or_default(),
+            &serde_json::to_value(&settings).unwrap_or_default(),
+            &mut changes,
+        );
+
+        Ok(ImportPlan {
+            settings,
+            vault_entries,
+            preview: ImportPreview {
+                changes,
+                secrets: secret_descriptions,
+                secrets_skipped,
+            },
+        })
+    }
+}
+
+/// A secret worth storing: not blank, and not the mask from a settings view exported by hand.
+fn unmasked(value: Option<String>) -> Option<String> {
+    trimmed(value).filter(|value| value != MASK)
+}
+
+/// Collects the leaves that differ between two JSON documents, as dotted paths.
+fn diff(path: &str, current: &Value, incoming: &Value, changes: &mut Vec<SettingChange>) {
+    if let (Value::Object(current), Value::Object(incoming)) = (current, incoming) {
+        let keys: BTreeSet<&String> = current.keys().chain(incoming.keys()).collect();
+        for key in keys {
+            let child = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            diff(
+                &child,
+                current.get(key).unwrap_or(&Value::Null),
+                incoming.get(key).unwrap_or(&Value::Null),
+                changes,
+            );
+        }
+        return;
+    }
+    if current == incoming {
+        return;
+    }
+    let masked = path
+        .rsplit('.')
+        .next()
+        .is_some_and(|field| secrets::SETTINGS_SECRETS.contains(&field));
+    let mask = |value: &Value| {
+        if masked && !value.is_null() {
+            Value::from(MASK)
+        } else {
+            value.clone()
+        }
+    };
+    changes.push(SettingChange {
+        path: path.to_string(),
+        current: mask(current),
+        incoming: mask(incoming),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{diff, SettingsBundle, MASK};
+    use crate::{secrets, settings::Settings};
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    fn settings(document: Value) -> Settings {
+        serde_json::from_value(document).unwrap()
+    }
+
+    fn exporting() -> Settings {
+        settings(json!({
+            "schemaVersion": 1,
+            "activeProfile": "Work",
+            "profiles": { "Work": { "modelPrompt": "ai/gemma3", "screenName": "alice" } },
+            "hubUrl": "https://hub.example"
+        }))
+    }
+
+    fn importing() -> Settings {
+        settings(json!({
+            "schemaVersion": 1,
+            "activeProfile": "Default",
+            "profiles": { "Default": {} }
+        }))
+    }
+
+    /// Exports `exporting()` with its secrets sealed, reading each vault entry from `vault`,
+    /// then reads the bundle back as an importing machine would.
+    fn round_trip(vault: impl Fn(&str) -> String) -> SettingsBundle {
+        let bundle = SettingsBundle::export(&exporting(), Some(PASSPHRASE), |entry| {
+            Ok(Some(vault(entry)))
+        })
+        .unwrap();
+        SettingsBundle::parse(&serde_json::to_string(&bundle).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sealed_secrets_round_trip_with_the_passphrase() {
+        let plan = round_trip(|entry| format!("secret for {entry}"))
+            .plan(&importing(), Some(PASSPHRASE))
+            .unwrap();
+
+        let mut entries = plan.vault_entries.clone();
+        entries.sort();
+        let expected = [
+            secrets::INFERENCE_API_KEY.to_string(),
+            secrets::SHARED_NANOBANANA_KEY.to_string(),
+            secrets::profile_nanobanana_key("Work"),
+            secrets::ZOOM_CLIENT_SECRET.to_string(),
+        ];
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(entry, _)| entry.clone())
+                .collect::<Vec<_>>(),
+            expected
+        );
+        for (entry, value) in &entries {
+            assert_eq!(value, &format!("secret for {entry}"));
+        }
+        assert_eq!(plan.preview.secrets.len(), 4);
+        assert!(!plan.preview.secrets_skipped);
+    }
+
+    #[test]
+    fn a_wrong_passphrase_is_an_error_and_none_skips_the_secrets() {
+        let bundle = round_trip(|entry| format!("secret for {entry}"));
+
+        let err = bundle
+            .plan(&importing(), Some("wrong horse battery"))
+            .err()
+            .unwrap();
+        assert!(err.contains("does not unlock"), "{err}");
+
+        let plan = bundle.plan(&importing(), None).unwrap();
+        assert!(plan.vault_entries.is_empty());
+        assert!(plan.preview.secrets_skipped);
+    }
+
+    #[test]
+    fn the_preview_lists_changed_settings_but_not_personal_ones() {
+        let bundle = SettingsBundle::export(&exporting(), None, |_| Ok(None)).unwrap();
+        let mut current = importing();
+        current.create_profile("Work", None).unwrap();
+        current.profiles.get_mut("Work").unwrap().screen_name = Some("bob".to_string());
+        let plan = bundle.plan(&current, None).unwrap();
+
+        let shown: Vec<(&str, &Value, &Value)> = plan
+            .preview
+            .changes
+            .iter()
+            .map(|change| (change.path.as_str(), &change.current, &change.incoming))
+            .collect();
+        assert_eq!(
+            shown,
+            [
+                ("hubUrl", &Value::Null, &json!("https://hub.example")),
+                (
+                    "profiles.Work.modelPrompt",
+                    &Value::Null,
+                    &json!("ai/gemma3")
+                ),
+            ]
+        );
+        assert_eq!(plan.preview.change_count(), 2);
+        assert_eq!(
+            plan.settings.profiles["Work"].screen_name.as_deref(),
+            Some("bob")
+        );
+    }
+
+    #[test]
+    fn the_preview_shows_a_new_profile_whole() {
+        let bundle = SettingsBundle::export(&exporting(), None, |_| Ok(None)).unwrap();
+        let plan = bundle.plan(&importing(), None).unwrap();
+
+        let change = plan
+            .preview
+            .changes
+            .iter()
+            .find(|change| change.path == "profiles.Work")
+            .unwrap();
+        assert_eq!(change.current, Value::Null);
+        assert_eq!(change.incoming["modelPrompt"], "ai/gemma3");
+        assert_eq!(change.incoming["screenName"], Value::Null);
+    }
+
+    #[test]
+    fn the_preview_masks_secrets() {
+        let mut changes = Vec::new();
+        diff(
+            "",
+            &json!({ "zoomClientSecret": null, "inferenceApiKey": "old", "hubUrl": "a" }),
+            &json!({ "zoomClientSecret": "new", "inferenceApiKey": null, "hubUrl": "b" }),
+            &mut changes,
+        );
+        let shown: Vec<(&str, &Value, &Value)> = changes
+            .iter()
+            .map(|change| (change.path.as_str(), &change.current, &change.incoming))
+            .collect();
+        assert_eq!(
+            shown,
+            [
+                ("hubUrl", &json!("a"), &json!("b")),
+                ("inferenceApiKey", &json!(MASK), &Value::Null),
+                ("zoomClientSecret", &Value::Null, &json!(MASK)),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_never_stores_the_mask_in_the_vault() {
+        let plan = round_trip(|_| MASK.to_string())
+            .plan(&importing(), Some(PASSPHRASE))
+            .unwrap();
+        assert!(plan.vault_entries.is_empty(), "{:?}", plan.vault_entries);
+        assert!(plan.preview.secrets.is_empty());
+    }
+}
@@ -0,0 +1,902 @@
+//! Combines settings from every source. From lowest to highest precedence: built-in defaults,
+//! the system-wide settings file, the user's `settings.json`, `SLOWLY_UNHINGED_*` environment
+//! variables, and `--<setting>` command-line flags.
+
+use std::{collections::BTreeMap, env, fs, io::ErrorKind, path::PathBuf, sync::OnceLock};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{
+    Settings, DEFAULT_BACKGROUND_LONG_POLL_TIMEOUT_SECS, DEFAULT_DMR_BASE_URL,
+    DEFAULT_DMR_MODEL_POLL_ATTEMPTS, DEFAULT_DMR_MODEL_POLL_DELAY_MS,
+    DEFAULT_DMR_PULL_STALL_TIMEOUT_SECS, DEFAULT_DMR_WARMUP_ATTEMPTS,
+    DEFAULT_HUB_HEARTBEAT_INTERVAL_SECS, DEFAULT_NANOBANANA_ASPECT_RATIO,
+    DEFAULT_NANOBANANA_ENDPOINT, DEFAULT_NANOBANANA_MODEL,
+};
+use crate::{docker, hub, secrets, zoom_auth};
+
+/// Overrides where the system-wide settings file is read from.
+const SYSTEM_SETTINGS_ENV: &str = "SLOWLY_UNHINGED_SYSTEM_SETTINGS";
+const ENV_PREFIX: &str = "SLOWLY_UNHINGED_";
+pub(super) const MASK: &str = "••••••";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Machine,
+    /// Applies to every profile.
+    Profile,
+}
+
+/// How a value given as text (an environment variable or flag) is read.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    Json,
+}
+
+struct SettingSpec {
+    key: &'static str,
+    scope: Scope,
+    kind: Kind,
+    secret: bool,
+    default: fn() -> Value,
+}
+
+const fn setting(
+    key: &'static str,
+    scope: Scope,
+    kind: Kind,
+    default: fn() -> Value,
+) -> SettingSpec {
+    SettingSpec {
+        key,
+        scope,
+        kind,
+        secret: false,
+        default,
+    }
+}
+
+/// Every setting that can be layered. Internal state such as wheel positions and the last
+/// screen name only lives in `settings.json`.
+const SETTINGS: &[SettingSpec] = &[
+    // The default models depend on the inference backend; `describe` fills them in.
+    setting("modelTranscription", Scope::Profile, Kind::Text, || {
+        Value::Null
+    }),
+    setting("modelPrompt", Scope::Profile, Kind::Text, || Value::Null),
+    setting(
+        "modelTranscriptionFallbacks",
+        Scope::Profile,
+        Kind::Json,
+        || Value::Array(Vec::new()),
+    ),
+    setting("modelPromptFallbacks", Scope::Profile, Kind::Json, || {
+        Value::Array(Vec::new())
+    }),
+    setting("backgroundStyle", Scope::Profile, Kind::Text, || {
+        Value::Null
+    }),
+    setting("nanobananaKeySource", Scope::Profile, Kind::Text, || {
+        "auto".into()
+    }),
+    setting("cloudflaredImage", Scope::Machine, Kind::Json, || {
+        serde_json::to_value(docker::ContainerImage::cloudflared()).unwrap_or_default()
+    }),
+    setting("hubUrl", Scope::Machine, Kind::Text, || {
+        hub::DEFAULT_HUB_URL.into()
+    }),
+    setting("hubRegisterPath", Scope::Machine, Kind::Text, || {
+        hub::DEFAULT_HUB_REGISTER_PATH.into()
+    }),
+    setting("hubUnregisterPath", Scope::Machine, Kind::Text, || {
+        hub::DEFAULT_HUB_UNREGISTER_PATH.into()
+    }),
+    setting("hubHeartbeatPath", Scope::Machine, Kind::Text, || {
+        hub::DEFAULT_HUB_HEARTBEAT_PATH.into()
+    }),
+    setting("hubReleaseIdentityPath", Scope::Machine, Kind::Text, || {
+        hub::DEFAULT_HUB_RELEASE_IDENTITY_PATH.into()
+    }),
+    setting(
+        "hubHeartbeatIntervalSecs",
+        Scope::Machine,
+        Kind::Json,
+        || DEFAULT_HUB_HEARTBEAT_INTERVAL_SECS.into(),
+    ),
+    setting("zoomClientId", Scope::Machine, Kind::Text, || Value::Null),
+    SettingSpec {
+        secret: true,
+        ..setting(
+            secrets::ZOOM_CLIENT_SECRET,
+            Scope::Machine,
+            Kind::Text,
+            || Value::Null,
+        )
+    },
+    setting("zoomAuthorizeUrl", Scope::Machine, Kind::Text, || {
+        zoom_auth::DEFAULT_ZOOM_AUTHORIZE_URL.into()
+    }),
+    setting("zoomTokenUrl", Scope::Machine, Kind::Text, || {
+        zoom_auth::DEFAULT_ZOOM_TOKEN_URL.into()
+    }),
+    setting("zoomUserUrl", Scope::Machine, Kind::Text, || {
+        zoom_auth::DEFAULT_ZOOM_USER_URL.into()
+    }),
+    setting("inferenceBackend", Scope::Machine, Kind::Text, || {
+        "dmr".into()
+    }),
+    setting("inferenceBaseUrl", Scope::Machine, Kind::Text, || {
+        Value::Null
+    }),
+    SettingSpec {
+        secret: true,
+        ..setting(
+            secrets::INFERENCE_API_KEY,
+            Scope::Machine,
+            Kind::Text,
+            || Value::Null,
+        )
+    },
+    setting("dmrBaseUrl", Scope::Machine, Kind::Text, || {
+        DEFAULT_DMR_BASE_URL.into()
+    }),
+    setting("dmrWarmupAttempts", Scope::Machine, Kind::Json, || {
+        DEFAULT_DMR_WARMUP_ATTEMPTS.into()
+    }),
+    setting("dmrModelPollAttempts", Scope::Machine, Kind::Json, || {
+        DEFAULT_DMR_MODEL_POLL_ATTEMPTS.into()
+    }),
+    setting("dmrModelPollDelayMs", Scope::Machine, Kind::Json, || {
+        DEFAULT_DMR_MODEL_POLL_DELAY_MS.into()
+    }),
+    setting(
+        "dmrPullStallTimeoutSecs",
+        Scope::Machine,
+        Kind::Json,
+        || DEFAULT_DMR_PULL_STALL_TIMEOUT_SECS.into(),
+    ),
+    setting("modelDownloadBudgetMb", Scope::Machine, Kind::Json, || {
+        Value::Null
+    }),
+    setting("nanobananaModel", Scope::Machine, Kind::Text, || {
+        DEFAULT_NANOBANANA_MODEL.into()
+    }),
+    setting("nanobananaEndpoint", Scope::Machine, Kind::Text, || {
+        DEFAULT_NANOBANANA_ENDPOINT.into()
+    }),
+    setting("nanobananaAspectRatio", Scope::Machine, Kind::Text, || {
+        DEFAULT_NANOBANANA_ASPECT_RATIO.into()
+    }),
+    setting(
+        "backgroundLongPollTimeoutSecs",
+        Scope::Machine,
+        Kind::Json,
+        || DEFAULT_BACKGROUND_LONG_POLL_TIMEOUT_SECS.into(),
+    ),
+];
+
+/// Where an effective value came from.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ConfigSource {
+    Default,
+    System,
+    User,
+    Environment,
+    CommandLine,
+    /// The encrypted secrets vault.
+    Vault,
+    /// A secret delivered by the Hub.
+    Hub,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConfigEntry {
+    key: String,
+    /// Secrets are masked.
+    value: Value,
+    source: ConfigSource,
+    /// The file, environment variable, flag, or vault entry that supplied the value.
+    origin: Option<String>,
+    /// Set for per-profile settings, which are reported for the active profile.
+    profile: Option<String>,
+}
+
+impl ConfigEntry {
+    /// Reports a secret resolved outside the settings layers, such as an API key.
+    pub(crate) fn secret(key: &str, resolved: Option<(ConfigSource, String)>) -> Self {
+        let (value, source, origin) = match resolved {
+            Some((source, origin)) => (MASK.into(), source, Some(origin)),
+            None => (Value::Null, ConfigSource::Default, None),
+        };
+        Self {
+            key: key.to_string(),
+            value,
+            source,
+            origin,
+            profile: None,
+        }
+    }
+}
+
+/// What `describe_config` returns: every effective value and the layer that supplied it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConfigReport {
+    entries: Vec<ConfigEntry>,
+    /// Values from the system file, environment, or flags that were ignored, and why.
+    warnings: Vec<String>,
+    system_settings_path: Option<String>,
+}
+
+impl ConfigReport {
+    pub(super) fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub(crate) fn push(&mut self, entry: ConfigEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Credits `key` to the vault entry of the same name where the report says `settings.json`
+    /// supplied it.
+    pub(super) fn credit_vault(&mut self, key: &str) {
+        for entry in &mut self.entries {
+            if entry.key == key && entry.source == ConfigSource::User {
+                entry.source = ConfigSource::Vault;
+                entry.origin = Some(key.to_string());
+            }
+        }
+    }
+
+    /// Reports `value` for `key` where no layer set it and the spec has no fixed default.
+    pub(super) fn fill_default(&mut self, key: &str, value: Value) {
+        for entry in &mut self.entries {
+            if entry.key == key && entry.source == ConfigSource::Default && entry.value.is_null() {
+                entry.value = value.clone();
+            }
+        }
+    }
+}
+
+struct Candidate {
+    value: Value,
+    source: ConfigSource,
+    origin: String,
+}
+
+/// Values from the layers around `settings.json`, keyed by setting.
+#[derive(Default)]
+struct Overlays {
+    /// Below `settings.json`.
+    system: BTreeMap<&'static str, Candidate>,
+    /// Above `settings.json`, highest precedence first.
+    overrides: Vec<BTreeMap<&'static str, Candidate>>,
+    warnings: Vec<String>,
+    system_path: Option<PathBuf>,
+}
+
+impl Overlays {
+    fn read() -> Self {
+        let mut overlays = Self {
+            system_path: system_settings_path(),
+            ..Self::default()
+        };
+        overlays.system = overlays.read_system_file();
+        let environment = overlays.read_environment();
+        let command_line = overlays.read_command_line();
+        overlays.overrides = vec![command_line, environment];
+        overlays
+    }
+
+    fn read_system_file(&mut self) -> BTreeMap<&'static str, Candidate> {
+        let mut values = BTreeMap::new();
+        let Some(path) = self.system_path.clone() else {
+            return values;
+        };
+        let origin = path.display().to_string();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return values,
+            Err(err) => {
+                self.warnings
+                    .push(format!("Could not read \"{origin}\": {err}"));
+                return values;
+            }
+        };
+        let document = match serde_json::from_str::<Map<String, Value>>(&contents) {
+            Ok(document) => document,
+            Err(err) => {
+                self.warnings.push(format!(
+                    "Ignoring \"{origin}\": it is not a JSON object ({err})."
+                ));
+                return values;
+            }
+        };
+        for (key, value) in document {
+            let Some(spec) = SETTINGS.iter().find(|spec| spec.key == key) else {
+                self.warnings.push(format!(
+                    "Ignoring unknown setting \"{key}\" in \"{origin}\"."
+                ));
+                continue;
+            };
+            if !value.is_null() {
+                values.insert(
+                    spec.key,
+                    Candidate {
+                        value,
+                        source: ConfigSource::System,
+                        origin: origin.clone(),
+                    },
+                );
+            }
+        }
+        values
+    }
+
+    fn read_environment(&mut self) -> BTreeMap<&'static str, Candidate> {
+        let mut values = BTreeMap::new();
+        for spec in SETTINGS {
+            let name = env_var_name(spec.key);
+            let Ok(raw) = env::var(&name) else { continue };
+            self.insert_text(&mut values, spec, &raw, ConfigSource::Environment, name);
+        }
+        values
+    }
+
+    fn read_command_line(&mut self) -> BTreeMap<&'static str, Candidate> {
+        let mut values = BTreeMap::new();
+        for (flag, raw) in command_line_flags() {
+            if let Some(spec) = SETTINGS.iter().find(|spec| flag_name(spec.key) == *flag) {
+                self.insert_text(
+                    &mut values,
+                    spec,
+                    raw,
+                    ConfigSource::CommandLine,
+                    flag.clone(),
+                );
+            }
+        }
+        values
+    }
+
+    fn insert_text(
+        &mut self,
+        values: &mut BTreeMap<&'static str, Candidate>,
+        spec: &SettingSpec,
+        raw: &str,
+        source: ConfigSource,
+        origin: String,
+    ) {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return;
+        }
+        let value = match spec.kind {
+            Kind::Text => Value::String(raw.to_string()),
+            Kind::Json => match serde_json::from_str(raw) {
+                Ok(value) => value,
+                Err(err) => {
+                    self.warnings.push(format!(
+                        "Ignoring {origin}: \"{raw}\" is not valid JSON ({err})."
+                    ));
+                    return;
+                }
+            },
+        };
+        values.insert(
+            spec.key,
+            Candidate {
+                value,
+                source,
+                origin,
+            },
+        );
+    }
+}
+
+/// `hubUrl` becomes `SLOWLY_UNHINGED_HUB_URL`.
+fn env_var_name(key: &str) -> String {
+    let mut name = ENV_PREFIX.to_string();
+    for ch in key.chars() {
+        if ch.is_ascii_uppercase() {
+            name.push('_');
+        }
+        name.push(ch.to_ascii_uppercase());
+    }
+    name
+}
+
+/// `hubUrl` becomes `--hub-url`.
+fn flag_name(key: &str) -> String {
+    let mut name = "--".to_string();
+    for ch in key.chars() {
+        if ch.is_ascii_uppercase() {
+            name.push('-');
+        }
+        name.push(ch.to_ascii_lowercase());
+    }
+    name
+}
+
+/// `--name value` and `--name=value` pairs from the process arguments.
+fn command_line_flags() -> &'static [(String, String)] {
+    static FLAGS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+    FLAGS.get_or_init(|| {
+        let mut flags = Vec::new();
+        let mut args = env::args().skip(1).peekable();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                continue;
+            }
+            match arg.split_once('=') {
+                Some((name, value)) => flags.push((name.to_string(), value.to_string())),
+                None => {
+                    if let Some(value) = args.next_if(|next| !next.starts_with("--")) {
+                        flags.push((arg, value));
+                    }
+                }
+            }
+        }
+        flags
+    })
+}
+
+fn system_settings_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(SYSTEM_SETTINGS_ENV).filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(target_os = "windows") {
+        let program_data = env::var_os("ProgramData")?;
+        Some(
+            PathBuf::from(program_data)
+                .join("Slowly Unhinged")
+                .join("settings.json"),
+        )
+    } else if cfg!(target_os = "macos") {
+        Some(PathBuf::from(
+            "/Library/Application Support/com.slowlyunhinged.agent/settings.json",
+        ))
+    } else {
+        Some(PathBuf::from("/etc/slowly-unhinged/settings.json"))
+    }
+}
+
+/// Sets `key` on the document itself or on every profile in it.
+fn set(document: &mut Value, spec: &SettingSpec, value: &Value) {
+    match spec.scope {
+        Scope::Machine => document[spec.key] = value.clone(),
+        Scope::Profile => {
+            if let Some(profiles) = document["profiles"].as_object_mut() {
+                for profile in profiles.values_mut() {
+                    profile[spec.key] = value.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Checks that `value` alone would leave `base` valid. A Hub override is checked against the
+/// Hub settings too, so that a bad one falls back to the layer below instead of stopping the
+/// agent from starting.
+fn accepts(base: &Value, spec: &SettingSpec, value: &Value) -> Result<(), String> {
+    let mut document = base.clone();
+    set(&mut document, spec, value);
+    let settings = serde_json::from_value::<Settings>(document).map_err(|err| err.to_string())?;
+    settings.validate()?;
+    if spec.key.starts_with("hub") {
+        settings.validate_hub()?;
+    }
+    Ok(())
+}
+
+/// The effective settings for `user` and where each value came from. Invalid values from
+/// the other layers are skipped and reported as warnings.
+pub(super) fn apply(user: &Settings) -> (Settings, ConfigReport) {
+    let overlays = Overlays::read();
+    let (settings, mut report) = combine(user, &overlays);
+    match settings.validate() {
+        Ok(()) => (settings, report),
+        Err(err) => {
+            // Each override was valid on its own; together they are not.
+            let (_, mut fallback) = combine(user, &Overlays::default());
+            fallback.warnings = report.warnings;
+            fallback.warnings.push(format!(
+                "Ignoring the system file, environment, and flags: together they are invalid ({err})."
+            ));
+            fallback.system_settings_path = report.system_settings_path.take();
+            (user.clone(), fallback)
+        }
+    }
+}
+
+fn combine(user: &Settings, overlays: &Overlays) -> (Settings, ConfigReport) {
+    let base = serde_json::to_value(user).unwrap_or_default();
+    let mut document = base.clone();
+    let mut warnings = overlays.warnings.clone();
+    let mut valid = |candidate: &Candidate, spec: &SettingSpec| {
+        accepts(&base, spec, &candidate.value)
+            .map_err(|err| {
+                warnings.push(format!(
+                    "Ignoring \"{}\" from {}: {err}",
+                    spec.key, candidate.origin
+                ))
+            })
+            .is_ok()
+    };
+
+    let mut entries = Vec::new();
+    for spec in SETTINGS {
+        let overriding = overlays
+            .overrides
+            .iter()
+            .filter_map(|layer| layer.get(spec.key))
+            .find(|candidate| valid(candidate, spec));
+        let system = overlays
+            .system
+            .get(spec.key)
+            .filter(|candidate| valid(candidate, spec));
+
+        let targets: Vec<(Option<String>, &mut Map<String, Value>)> = match spec.scope {
+            Scope::Machine => document
+                .as_object_mut()
+                .map(|root| vec![(None, root)])
+                .unwrap_or_default(),
+            Scope::Profile => document["profiles"]
+                .as_object_mut()
+                .map(|profiles| {
+                    profiles
+                        .iter_mut()
+                        .filter_map(|(name, profile)| {
+                            Some((Some(name.clone()), profile.as_object_mut()?))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        for (profile, target) in targets {
+            let current = target.get(spec.key).filter(|value| !value.is_null());
+            let (value, source, origin) = match (overriding, current, system) {
+                (Some(candidate), _, _) => (
+                    candidate.value.clone(),
+                    candidate.source,
+                    Some(candidate.origin.clone()),
+                ),
+                (None, Some(value), _) => (value.clone(), ConfigSource::User, None),
+                (None, None, Some(candidate)) => (
+                    candidate.value.clone(),
+                    candidate.source,
+                    Some(candidate.origin.clone()),
+                ),
+                (None, None, None) => ((spec.default)(), ConfigSource::Default, None),
+            };
+            if source != ConfigSource::Default {
+                target.insert(spec.key.to_string(), value.clone());
+            }
+
+            let reported = match &profile {
+                None => true,
+                Some(name) => name == user.active_profile_name(),
+            };
+            if reported {
+                entries.push(ConfigEntry {
+                    key: spec.key.to_string(),
+                    value: if spec.secret && !value.is_null() {
+                        MASK.into()
+                    } else {
+                        value
+                    },
+                    source,
+                    origin,
+                    profile,
+                });
+            }
+        }
+    }
+
+    let settings = serde_json::from_value(document).unwrap_or_else(|err| {
+        warnings.push(format!("Could not combine the settings layers: {err}"));
+        user.clone()
+    });
+    (
+        settings,
+        ConfigReport {
+            entries,
+            warnings,
+            system_settings_path: overlays
+                .system_path
+                .as_ref()
+                .map(|path| path.display().to_string()),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, process};
+
+    use serde_json::{json, Value};
+
+    use super::{
+        combine, env_var_name, flag_name, Candidate, ConfigEntry, ConfigReport, ConfigSource,
+        Overlays, SettingSpec, MASK, SETTINGS,
+    };
+    use crate::{hub, settings::Settings};
+
+    const SYSTEM_URL: &str = "https://system.example";
+    const USER_URL: &str = "https://user.example";
+    const ENV_URL: &str = "https://env.example";
+    const FLAG_URL: &str = "https://flag.example";
+
+    fn spec(key: &str) -> &'static SettingSpec {
+        SETTINGS.iter().find(|spec| spec.key == key).unwrap()
+    }
+
+    fn user(document: Value) -> Settings {
+        let mut document = document;
+        document["activeProfile"] = "Work".into();
+        if document.get("profiles").is_none() {
+            document["profiles"] = json!({ "Work": {}, "Home": {} });
+        }
+        serde_json::from_value(document).unwrap()
+    }
+
+    fn candidate(value: impl Into<Value>, source: ConfigSource, origin: &str) -> Candidate {
+        Candidate {
+            value: value.into(),
+            source,
+            origin: origin.to_string(),
+        }
+    }
+
+    /// Overlays holding `key` from each of the given layers.
+    fn overlays(
+        key: &'static str,
+        system: Option<Value>,
+        env: Option<Value>,
+        flag: Option<Value>,
+    ) -> Overlays {
+        let layer = |value: Option<Value>, source, origin: &str| {
+            value
+                .map(|value| BTreeMap::from([(key, candidate(value, source, origin))]))
+                .unwrap_or_default()
+        };
+        Overlays {
+            system: layer(system, ConfigSource::System, "system.json"),
+            overrides: vec![
+                layer(flag, ConfigSource::CommandLine, "--flag"),
+                layer(env, ConfigSource::Environment, "ENV"),
+            ],
+            ..Overlays::default()
+        }
+    }
+
+    fn entry<'a>(report: &'a ConfigReport, key: &str) -> &'a ConfigEntry {
+        report
+            .entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .unwrap()
+    }
+
+    #[test]
+    fn env_var_and_flag_names_follow_the_setting_key() {
+        for (key, env_var, flag) in [
+            ("hubUrl", "SLOWLY_UNHINGED_HUB_URL", "--hub-url"),
+            (
+                "modelTranscriptionFallbacks",
+                "SLOWLY_UNHINGED_MODEL_TRANSCRIPTION_FALLBACKS",
+                "--model-transcription-fallbacks",
+            ),
+            (
+                "dmrBaseUrl",
+                "SLOWLY_UNHINGED_DMR_BASE_URL",
+                "--dmr-base-url",
+            ),
+            (
+                "zoomClientSecret",
+                "SLOWLY_UNHINGED_ZOOM_CLIENT_SECRET",
+                "--zoom-client-secret",
+            ),
+        ] {
+            assert_eq!(env_var_name(key), env_var, "{key}");
+            assert_eq!(flag_name(key), flag, "{key}");
+        }
+    }
+
+    #[test]
+    fn higher_layers_win() {
+        let url = |url: &str| Some(Value::from(url));
+        for (system, user_url, env, flag, expected, source) in [
+            (
+                None,
+                None,
+                None,
+                None,
+                hub::DEFAULT_HUB_URL,
+                ConfigSource::Default,
+            ),
+            (
+                url(SYSTEM_URL),
+                None,
+                None,
+                None,
+                SYSTEM_URL,
+                ConfigSource::System,
+            ),
+            (
+                url(SYSTEM_URL),
+                url(USER_URL),
+                None,
+                None,
+                USER_URL,
+                ConfigSource::User,
+            ),
+            (
+                None,
+                url(USER_URL),
+                url(ENV_URL),
+                None,
+                ENV_URL,
+                ConfigSource::Environment,
+            ),
+            (
+                url(SYSTEM_URL),
+                url(USER_URL),
+                url(ENV_URL),
+                url(FLAG_URL),
+                FLAG_URL,
+                ConfigSource::CommandLine,
+            ),
+            (
+                None,
+                None,
+                None,
+                url(FLAG_URL),
+                FLAG_URL,
+                ConfigSource::CommandLine,
+            ),
+        ] {
+            let user = user(json!({ "hubUrl": user_url }));
+            let (settings, report) = combine(&user, &overlays("hubUrl", system, env, flag));
+            let entry = entry(&report, "hubUrl");
+            assert_eq!(entry.value, expected);
+            assert_eq!(entry.source, source, "{expected}");
+            let origin = match source {
+                ConfigSource::System => Some("system.json"),
+                ConfigSource::Environment => Some("ENV"),
+                ConfigSource::CommandLine => Some("--flag"),
+                _ => None,
+            };
+            assert_eq!(entry.origin.as_deref(), origin, "{expected}");
+            let effective = settings.hub_url.as_deref().unwrap_or(hub::DEFAULT_HUB_URL);
+            assert_eq!(effective, expected);
+            assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        }
+    }
+
+    #[test]
+    fn profile_settings_layer_per_profile_and_report_the_active_one() {
+        let user = user(json!({
+            "profiles": { "Work": {}, "Home": { "modelPrompt": "ai/home" } }
+        }));
+        let overlays = overlays("modelPrompt", Some("ai/system".into()), None, None);
+        let (settings, report) = combine(&user, &overlays);
+
+        assert_eq!(
+            settings.profiles["Work"].model_prompt.as_deref(),
+            Some("ai/system")
+        );
+        assert_eq!(
+            settings.profiles["Home"].model_prompt.as_deref(),
+            Some("ai/home")
+        );
+        let entries: Vec<_> = report
+            .entries
+            .iter()
+            .filter(|entry| entry.key == "modelPrompt")
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].profile.as_deref(), Some("Work"));
+        assert_eq!(entries[0].source, ConfigSource::System);
+    }
+
+    #[test]
+    fn invalid_overrides_fall_back_to_the_layer_below() {
+        let user = user(json!({ "hubUrl": USER_URL }));
+        let overlays = overlays(
+            "hubUrl",
+            None,
+            Some("not a url".into()),
+            Some("ftp://flag.example".into()),
+        );
+        let (settings, report) = combine(&user, &overlays);
+
+        assert_eq!(settings.hub_url.as_deref(), Some(USER_URL));
+        assert_eq!(entry(&report, "hubUrl").source, ConfigSource::User);
+        assert_eq!(report.warnings.len(), 2, "{:?}", report.warnings);
+        assert!(report.warnings[0].contains("--flag"));
+        assert!(report.warnings[1].contains("ENV"));
+    }
+
+    #[test]
+    fn text_values_are_trimmed_and_json_values_parsed() {
+        let mut overlays = Overlays::default();
+        let mut values = BTreeMap::new();
+        let cases: [(&str, &str, Option<Value>); 4] = [
+            (
+                "hubUrl",
+                "  https://hub.example  ",
+                Some(json!("https://hub.example")),
+            ),
+            (
+                "modelPromptFallbacks",
+                r#"["ai/a", "ai/b"]"#,
+                Some(json!(["ai/a", "ai/b"])),
+            ),
+            ("dmrWarmupAttempts", "not json", None),
+            ("backgroundStyle", "   ", None),
+        ];
+        for (key, raw, _) in &cases {
+            overlays.insert_text(
+                &mut values,
+                spec(key),
+                raw,
+                ConfigSource::Environment,
+                env_var_name(key),
+            );
+        }
+        for (key, _, expected) in cases {
+            assert_eq!(values.get(key).map(|found| found.value.clone()), expected);
+        }
+        assert_eq!(overlays.warnings.len(), 1, "{:?}", overlays.warnings);
+        assert!(overlays.warnings[0].contains("SLOWLY_UNHINGED_DMR_WARMUP_ATTEMPTS"));
+    }
+
+    #[test]
+    fn system_file_skips_unknown_and_null_settings() {
+        let path = std::env::temp_dir().join(format!("layers-test-{}.json", process::id()));
+        fs::write(
+            &path,
+            json!({ "hubUrl": SYSTEM_URL, "modelPrompt": null, "bogus": 1 }).to_string(),
+        )
+        .unwrap();
+        let mut overlays = Overlays {
+            system_path: Some(path.clone()),
+            ..Overlays::default()
+        };
+        let values = overlays.read_system_file();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(values.keys().copied().collect::<Vec<_>>(), ["hubUrl"]);
+        assert_eq!(values["hubUrl"].source, ConfigSource::System);
+        assert_eq!(overlays.warnings.len(), 1, "{:?}", overlays.warnings);
+        assert!(overlays.warnings[0].contains("\"bogus\""));
+    }
+
+    #[test]
+    fn secrets_are_masked_in_the_report_only() {
+        let user = user(json!({}));
+        let overlays = overlays("zoomClientSecret", None, Some("s3cret".into()), None);
+        let (settings, report) = combine(&user, &overlays);
+
+        assert_eq!(settings.zoom_client_secret.as_deref(), Some("s3cret"));
+        let secret = entry(&report, "zoomClientSecret");
+        assert_eq!(secret.value, MASK);
+        assert_eq!(secret.source, ConfigSource::Environment);
+        assert_eq!(entry(&report, "inferenceApiKey").value, Value::Null);
+
+        let resolved = ConfigEntry::secret(
+            "nanobananaApiKey",
+            Some((ConfigSource::Vault, "nanobanana".to_string())),
+        );
+        assert_eq!(resolved.value, MASK);
+        assert_eq!(
+            ConfigEntry::secret("nanobananaApiKey", None).value,
+            Value::Null
+        );
+    }
+}
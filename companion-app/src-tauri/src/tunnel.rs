@@ -0,0 +1,348 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use rand_core::{OsRng, RngCore};
+use regex::Regex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{sleep, timeout},
+};
+
+use crate::{
+    docker::{LogLine, LogStream},
+    AppState,
+};
+
+const TUNNEL_LOG_CAPACITY: usize = 500;
+const TUNNEL_URL_TIMEOUT: Duration = Duration::from_secs(30);
+const CLOUDFLARED_LOG_EVENT: &str = "cloudflared-log";
+const TUNNEL_STATUS_EVENT: &str = "tunnel-status";
+const TUNNEL_VERIFY_TIMEOUT: Duration = Duration::from_secs(60);
+const TUNNEL_VERIFY_INTERVAL: Duration = Duration::from_secs(1);
+const TUNNEL_VERIFY_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+pub(crate) const TUNNEL_NONCE_HEADER: &str = "x-companion-nonce";
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TunnelLogEntry {
+    timestamp_ms: u64,
+    stream: LogStream,
+    line: String,
+}
+
+/// Fixed-size buffer of the most recent cloudflared log lines.
+pub(crate) struct TunnelLogBuffer {
+    entries: VecDeque<TunnelLogEntry>,
+}
+
+impl TunnelLogBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(TUNNEL_LOG_CAPACITY),
+        }
+    }
+
+    fn push(&mut self, entry: TunnelLogEntry) {
+        if self.entries.len() == TUNNEL_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn recent(&self, limit: usize) -> Vec<TunnelLogEntry> {
+        let skip = self.entries.len().saturating_sub(limit);
+        self.entries.iter().skip(skip).cloned().collect()
+    }
+
+    fn tail_text(&self, limit: usize) -> String {
+        self.recent(limit)
+            .into_iter()
+            .map(|entry| entry.line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum TunnelSignal {
+    UrlAssigned { url: String },
+    Connected,
+    Disconnected { detail: String },
+    RateLimited { detail: String },
+    Error { detail: String },
+    Exited,
+}
+
+fn tunnel_url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(https://[a-zA-Z0-9-]+\.trycloudflare\.com)").expect("valid tunnel URL regex")
+    })
+}
+
+fn classify(line: &str) -> Option<TunnelSignal> {
+    if let Some(capture) = tunnel_url_regex().captures(line).and_then(|c| c.get(1)) {
+        return Some(TunnelSignal::UrlAssigned {
+            url: capture.as_str().to_string(),
+        });
+    }
+
+    let lowered = line.to_ascii_lowercase();
+    let detail = line.trim().to_string();
+    // A bare "429" also turns up inside request IDs and hashes, so only cloudflared's own
+    // phrasings count.
+    if lowered.contains("status_code=429")
+        || lowered.contains("too many requests")
+        || lowered.contains("rate limit")
+    {
+        Some(TunnelSignal::RateLimited { detail })
+    } else if lowered.contains("unregistered tunnel connection")
+        || lowered.contains("connection terminated")
+        || lowered.contains("lost connection")
+        || lowered.contains("retrying connection")
+    {
+        // Checked first: "unregistered tunnel connection" contains "registered tunnel connection".
+        Some(TunnelSignal::Disconnected { detail })
+    } else if lowered.contains("registered tunnel connection") {
+        Some(TunnelSignal::Connected)
+    } else if lowered.contains(" err ") {
+        Some(TunnelSignal::Error { detail })
+    } else {
+        None
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn emit_signal(app: &AppHandle, signal: &TunnelSignal) {
+    if let Err(err) = app.emit(TUNNEL_STATUS_EVENT, signal) {
+        eprintln!("[cloudflared] Failed to emit tunnel status: {err}");
+    }
+}
+
+/// Forwards cloudflared log lines into the ring buffer and the UI for as long as the
+/// container runs, resolving with the tunnel URL once cloudflared reports it.
+pub(crate) async fn follow(
+    app: AppHandle,
+    state: Arc<AppState>,
+    mut lines: mpsc::UnboundedReceiver<LogLine>,
+) -> Result<String, String> {
+    state.tunnel_logs.lock().await.clear();
+
+    let (url_sender, url_receiver) = oneshot::channel::<Result<String, String>>();
+    let follower_state = state.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut url_sender = Some(url_sender);
+        while let Some(line) = lines.recv().await {
+            let entry = TunnelLogEntry {
+                timestamp_ms: now_ms(),
+                stream: line.stream,
+                line: line.text,
+            };
+            let signal = classify(&entry.line);
+            if let Err(err) = app.emit(CLOUDFLARED_LOG_EVENT, &entry) {
+                eprintln!("[cloudflared] Failed to emit log line: {err}");
+            }
+            follower_state.tunnel_logs.lock().await.push(entry);
+
+            let Some(signal) = signal else {
+                continue;
+            };
+            match &signal {
+                TunnelSignal::UrlAssigned { url } => {
+                    if let Some(sender) = url_sender.take() {
+                        let _ = sender.send(Ok(url.clone()));
+                    }
+                }
+                TunnelSignal::RateLimited { detail } => {
+                    println!("[cloudflared] Rate limited: {detail}");
+                    if let Some(sender) = url_sender.take() {
+                        let _ = sender.send(Err(format!(
+                            "Cloudflare is rate-limiting quick tunnel creation. Wait a few minutes and try again. ({detail})"
+                        )));
+                    }
+                }
+                TunnelSignal::Disconnected { detail } => {
+                    println!("[cloudflared] Tunnel connection lost: {detail}");
+                }
+                TunnelSignal::Error { .. } | TunnelSignal::Connected | TunnelSignal::Exited => {}
+            }
+            emit_signal(&app, &signal);
+        }
+
+        emit_signal(&app, &TunnelSignal::Exited);
+        if let Some(sender) = url_sender.take() {
+            let _ = sender.send(Err(
+                "cloudflared exited before reporting a tunnel URL.".to_string()
+            ));
+        }
+    });
+
+    let outcome = match timeout(TUNNEL_URL_TIMEOUT, url_receiver).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("cloudflared log follower stopped unexpectedly.".to_string()),
+        Err(_) => Err("Timed out waiting for cloudflared tunnel URL.".to_string()),
+    };
+
+    match outcome {
+        Ok(url) => Ok(url),
+        Err(err) => {
+            let tail = state.tunnel_logs.lock().await.tail_text(20);
+            if tail.is_empty() {
+                Err(err)
+            } else {
+                Err(format!("{err} Latest logs:\n{tail}"))
+            }
+        }
+    }
+}
+
+/// Random token the health check echoes so a verifier knows it reached this process.
+pub(crate) fn new_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Polls the public tunnel URL until the companion's health check answers with
+/// `nonce`, which means DNS has propagated and the tunnel routes to this instance.
+pub(crate) async fn verify_public_url(
+    client: &reqwest::Client,
+    tunnel_url: &str,
+    nonce: &str,
+) -> Result<(), String> {
+    let started = Instant::now();
+    let mut attempt = 0;
+    let mut last_error = String::from("no response yet");
+
+    while started.elapsed() < TUNNEL_VERIFY_TIMEOUT {
+        attempt += 1;
+        match client
+            .get(tunnel_url)
+            .timeout(TUNNEL_VERIFY_REQUEST_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => {
+                let echoed = response
+                    .headers()
+                    .get(TUNNEL_NONCE_HEADER)
+                    .and_then(|value| value.to_str().ok());
+                if echoed == Some(nonce) {
+                    println!(
+                        "[Tunnel] {tunnel_url} verified after {attempt} attempts ({} ms).",
+                        started.elapsed().as_millis()
+                    );
+                    return Ok(());
+                }
+                last_error = "health check answered by a different instance".to_string();
+            }
+            Ok(response) => last_error = format!("HTTP {}", response.status()),
+            Err(err) => last_error = err.to_string(),
+        }
+
+        println!("[Tunnel] Waiting for {tunnel_url} (attempt {attempt}): {last_error}");
+        sleep(TUNNEL_VERIFY_INTERVAL).await;
+    }
+
+    Err(format!(
+        "Tunnel {tunnel_url} did not reach this companion within {} seconds (last error: {last_error}). \
+         Check your network connection and try again.",
+        TUNNEL_VERIFY_TIMEOUT.as_secs()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, TunnelSignal};
+
+    /// Lines as cloudflared 2024.x prints them for a quick tunnel.
+    const QUICK_TUNNEL_URL: &str = "2024-11-04T12:00:03Z INF |  https://vocal-harbor-trim-lens.trycloudflare.com                                   |";
+    const REQUESTING: &str =
+        "2024-11-04T12:00:00Z INF Requesting new quick Tunnel on trycloudflare.com...";
+    const RATE_LIMITED: &str = "2024-11-04T12:00:01Z ERR Error unmarshaling QuickTunnel response: error code: 1015 error=\"invalid character 'e' looking for beginning of value\" status_code=\"429 Too Many Requests\"";
+    const REGISTERED: &str = "2024-11-04T12:00:04Z INF Registered tunnel connection connIndex=0 connection=4291c7e2-5b1d-4f9a-9d27-0c2b6e1a4290 event=0 ip=198.41.200.13 location=ams08 protocol=quic";
+    const UNREGISTERED: &str = "2024-11-04T12:30:00Z INF Unregistered tunnel connection connIndex=0 event=0 ip=198.41.200.13";
+    const TERMINATED: &str = "2024-11-04T12:30:00Z ERR Connection terminated error=\"timeout: no recent network activity\" connIndex=0";
+    const RETRYING: &str = "2024-11-04T12:30:01Z INF Retrying connection in up to 2s connIndex=0 event=0 ip=198.41.200.13";
+    const UNRELATED_ERROR: &str = "2024-11-04T12:00:00Z ERR Failed to fetch features, default to disable error=\"lookup cfd-features.argotunnel.com on 127.0.0.11:53: no such host\"";
+    const ERROR_WITH_429: &str = "2024-11-04T12:31:00Z ERR Request failed error=\"stream 429 canceled by remote with error code 0\" connIndex=0";
+
+    fn detail(line: &str) -> String {
+        line.trim().to_string()
+    }
+
+    #[test]
+    fn classifies_cloudflared_log_lines() {
+        let cases = [
+            (
+                QUICK_TUNNEL_URL,
+                Some(TunnelSignal::UrlAssigned {
+                    url: "https://vocal-harbor-trim-lens.trycloudflare.com".to_string(),
+                }),
+            ),
+            (REQUESTING, None),
+            (
+                RATE_LIMITED,
+                Some(TunnelSignal::RateLimited {
+                    detail: detail(RATE_LIMITED),
+                }),
+            ),
+            (REGISTERED, Some(TunnelSignal::Connected)),
+            (
+                UNREGISTERED,
+                Some(TunnelSignal::Disconnected {
+                    detail: detail(UNREGISTERED),
+                }),
+            ),
+            (
+                TERMINATED,
+                Some(TunnelSignal::Disconnected {
+                    detail: detail(TERMINATED),
+                }),
+            ),
+            (
+                RETRYING,
+                Some(TunnelSignal::Disconnected {
+                    detail: detail(RETRYING),
+                }),
+            ),
+            (
+                UNRELATED_ERROR,
+                Some(TunnelSignal::Error {
+                    detail: detail(UNRELATED_ERROR),
+                }),
+            ),
+            ("2024-11-04T12:00:00Z INF Version 2024.10.1", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(classify(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn a_bare_429_is_not_rate_limiting() {
+        assert_eq!(classify(REGISTERED), Some(TunnelSignal::Connected));
+        assert_eq!(
+            classify(ERROR_WITH_429),
+            Some(TunnelSignal::Error {
+                detail: detail(ERROR_WITH_429),
+            })
+        );
+    }
+}
@@ -2,8 +2,9 @@ use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::{
     body::Body,
-    extract::{Query, State},
-    http::{header, HeaderValue, Response, StatusCode},
+    extract::{ConnectInfo, Query, State},
+    http::{header, HeaderMap, HeaderValue, Response, StatusCode},
+    response::{Html, IntoResponse},
     routing::{get, post},
     Json, Router,
 };
@@ -11,9 +12,12 @@ use serde::Deserialize;
 use tokio::net::TcpListener;
 use tokio::time::timeout;
 
-use crate::{AppState, BackgroundAsset, BACKEND_PORT};
-
-const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(25);
+use crate::{
+    settings::DEFAULT_BACKGROUND_LONG_POLL_TIMEOUT_SECS,
+    tunnel::TUNNEL_NONCE_HEADER,
+    zoom_auth::{self, ZOOM_CALLBACK_PATH},
+    AppState, BackgroundAsset, BACKEND_PORT,
+};
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,11 +26,20 @@ struct BackgroundLatestQuery {
     wait: Option<bool>,
 }
 
+#[derive(Debug, Default, Deserialize)]
+struct ZoomCallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
 pub async fn run(state: Arc<AppState>) -> Result<(), String> {
     let router = Router::new()
         .route("/", get(root_health_check))
         .route("/background/latest", get(background_latest))
         .route("/internal/secrets/nanobanana", post(set_nanobanana_secret))
+        .route(ZOOM_CALLBACK_PATH, get(zoom_callback))
         .with_state(state);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], BACKEND_PORT));
@@ -36,9 +49,12 @@ pub async fn run(state: Arc<AppState>) -> Result<(), String> {
 
     println!("[HTTP] Companion API listening on http://{addr}");
 
-    axum::serve(listener, router)
-        .await
-        .map_err(|err| format!("Companion HTTP server error: {err}"))
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(|err| format!("Companion HTTP server error: {err}"))
 }
 
 async fn background_latest(
@@ -82,8 +98,67 @@ async fn background_latest(
     }
 }
 
-async fn root_health_check() -> &'static str {
-    "slowly unhinged tunnel working"
+async fn root_health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(TUNNEL_NONCE_HEADER, state.tunnel_nonce.clone())],
+        "slowly unhinged tunnel working",
+    )
+}
+
+/// OAuth redirect target for Zoom sign-in. Only answers connections from this machine
+/// addressed to a loopback host, so the public tunnel (which reaches the server from the
+/// cloudflared container) cannot be used to inject an authorization code.
+async fn zoom_callback(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<ZoomCallbackQuery>,
+) -> (StatusCode, Html<String>) {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let hostname = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    if !peer.ip().is_loopback() || !matches!(hostname, "127.0.0.1" | "localhost") {
+        return (StatusCode::NOT_FOUND, Html(String::new()));
+    }
+
+    let Some(returned_state) = params.state else {
+        return sign_in_page(
+            StatusCode::BAD_REQUEST,
+            "The sign-in response is missing its state.",
+        );
+    };
+    let result = match (params.code, params.error) {
+        (_, Some(error)) => Err(format!(
+            "Zoom sign-in failed: {}",
+            params.error_description.unwrap_or(error)
+        )),
+        (Some(code), None) => Ok(code),
+        (None, None) => Err("Zoom did not return an authorization code.".to_string()),
+    };
+    let succeeded = result.is_ok();
+
+    match zoom_auth::complete(&state, &returned_state, result).await {
+        Ok(()) if succeeded => sign_in_page(
+            StatusCode::OK,
+            "Signed in with Zoom. You can close this window and return to Slowly Unhinged.",
+        ),
+        Ok(()) => sign_in_page(
+            StatusCode::BAD_REQUEST,
+            "Zoom sign-in did not complete. Return to Slowly Unhinged and try again.",
+        ),
+        Err(err) => sign_in_page(StatusCode::BAD_REQUEST, &err),
+    }
+}
+
+fn sign_in_page(status: StatusCode, message: &str) -> (StatusCode, Html<String>) {
+    (
+        status,
+        Html(format!(
+            "<!doctype html><title>Slowly Unhinged</title><p>{message}</p>"
+        )),
+    )
 }
 
 fn build_response(
@@ -131,7 +206,15 @@ fn build_response(
 }
 
 async fn wait_for_update(state: &Arc<AppState>) -> bool {
-    timeout(LONG_POLL_TIMEOUT, state.background_notify.notified())
+    // The snapshot was validated when it was loaded, so the default only covers startup.
+    let long_poll_timeout = state
+        .settings
+        .snapshot()
+        .and_then(|settings| settings.background_long_poll_timeout().ok())
+        .unwrap_or(Duration::from_secs(
+            DEFAULT_BACKGROUND_LONG_POLL_TIMEOUT_SECS,
+        ));
+    timeout(long_poll_timeout, state.background_notify.notified())
         .await
         .is_ok()
 }
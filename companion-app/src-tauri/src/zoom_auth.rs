@@ -0,0 +1,359 @@
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL_SAFE;
+use base64::Engine;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+use tokio::{sync::oneshot, time::timeout};
+
+use crate::{settings::load_settings, AppState, BACKEND_PORT};
+
+pub(crate) const DEFAULT_ZOOM_AUTHORIZE_URL: &str = "https://zoom.us/oauth/authorize";
+pub(crate) const DEFAULT_ZOOM_TOKEN_URL: &str = "https://zoom.us/oauth/token";
+pub(crate) const DEFAULT_ZOOM_USER_URL: &str = "https://api.zoom.us/v2/users/me";
+pub(crate) const ZOOM_CLIENT_ID_ENV: &str = "SLOWLY_UNHINGED_ZOOM_CLIENT_ID";
+pub(crate) const ZOOM_CALLBACK_PATH: &str = "/oauth/zoom/callback";
+const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Zoom access tokens last an hour; used when the token response leaves out `expires_in`.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(3_600);
+/// Refresh this long before expiry so the Hub never receives a token that lapses in flight.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(120);
+
+/// Where the companion sends the user to sign in. Every URL can point at a local
+/// stand-in server so the flow can be exercised without a real Zoom app.
+pub(crate) struct ZoomOAuthConfig {
+    client_id: String,
+    client_secret: Option<String>,
+    authorize_url: String,
+    token_url: String,
+    user_url: String,
+}
+
+impl ZoomOAuthConfig {
+    pub(crate) fn resolve(
+        client_id: Option<&str>,
+        client_secret: Option<&str>,
+        authorize_url: Option<&str>,
+        token_url: Option<&str>,
+        user_url: Option<&str>,
+    ) -> Result<Self, String> {
+        let client_id = non_empty(client_id).ok_or_else(|| {
+            format!(
+                "Zoom sign-in is not configured. Set \"zoomClientId\" in settings or {ZOOM_CLIENT_ID_ENV}."
+            )
+        })?;
+
+        Ok(Self {
+            client_id: client_id.to_string(),
+            client_secret: non_empty(client_secret).map(str::to_string),
+            authorize_url: validate_url(
+                authorize_url,
+                "zoomAuthorizeUrl",
+                DEFAULT_ZOOM_AUTHORIZE_URL,
+            )?,
+            token_url: validate_url(token_url, "zoomTokenUrl", DEFAULT_ZOOM_TOKEN_URL)?,
+            user_url: validate_url(user_url, "zoomUserUrl", DEFAULT_ZOOM_USER_URL)?,
+        })
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+pub(crate) fn validate_url(
+    raw: Option<&str>,
+    field: &str,
+    default: &str,
+) -> Result<String, String> {
+    let Some(raw) = non_empty(raw) else {
+        return Ok(default.to_string());
+    };
+    match url::Url::parse(raw) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(raw.to_string()),
+        _ => Err(format!(
+            "Invalid \"{field}\" in settings: \"{raw}\" must be an http:// or https:// URL."
+        )),
+    }
+}
+
+/// An authorization request waiting for the browser to come back to the loopback server.
+pub(crate) struct PendingZoomSignIn {
+    state: String,
+    sender: oneshot::Sender<Result<String, String>>,
+}
+
+/// The Zoom account the companion signed in as. The tokens are only kept in memory; the
+/// access token is handed to the Hub, which verifies it before binding the account to the
+/// agent.
+pub(crate) struct ZoomSession {
+    pub(crate) account: ZoomAccount,
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Instant,
+}
+
+impl ZoomSession {
+    fn update_tokens(&mut self, token: TokenResponse) {
+        self.expires_at = token.expires_at();
+        self.access_token = token.access_token;
+        if token.refresh_token.is_some() {
+            self.refresh_token = token.refresh_token;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ZoomAccount {
+    pub(crate) user_id: String,
+    pub(crate) display_name: Option<String>,
+    pub(crate) email: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+impl TokenResponse {
+    fn expires_at(&self) -> Instant {
+        Instant::now()
+            + self
+                .expires_in
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_TOKEN_LIFETIME)
+    }
+}
+
+#[derive(Deserialize)]
+struct ZoomUserResponse {
+    id: String,
+    display_name: Option<String>,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    email: Option<String>,
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    BASE64_URL_SAFE.encode(bytes)
+}
+
+fn redirect_uri() -> String {
+    format!("http://127.0.0.1:{BACKEND_PORT}{ZOOM_CALLBACK_PATH}")
+}
+
+/// Hands the authorization code from the loopback callback to the waiting sign-in.
+pub(crate) async fn complete(
+    state: &AppState,
+    returned_state: &str,
+    result: Result<String, String>,
+) -> Result<(), String> {
+    let mut guard = state.zoom_sign_in.lock().await;
+    match guard.as_ref() {
+        Some(pending) if pending.state == returned_state => {}
+        Some(_) => return Err("This sign-in link is stale. Start the sign-in again.".to_string()),
+        None => return Err("No Zoom sign-in is in progress.".to_string()),
+    }
+    let pending = guard.take().expect("pending sign-in checked above");
+    let _ = pending.sender.send(result);
+    Ok(())
+}
+
+/// Runs the authorization-code flow with PKCE: opens the browser, waits for the loopback
+/// callback, exchanges the code, and looks up the signed-in Zoom user.
+pub(crate) async fn sign_in(
+    app: &AppHandle,
+    state: &AppState,
+    config: &ZoomOAuthConfig,
+) -> Result<ZoomSession, String> {
+    let verifier = random_token();
+    let challenge = BASE64_URL_SAFE.encode(Sha256::digest(verifier.as_bytes()));
+    let csrf_state = random_token();
+    let redirect_uri = redirect_uri();
+
+    let mut authorize_url = url::Url::parse(&config.authorize_url)
+        .map_err(|err| format!("Invalid Zoom authorize URL: {err}"))?;
+    authorize_url
+        .query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &redirect_uri)
+        .append_pair("state", &csrf_state)
+        .append_pair("code_challenge", &challenge)
+        .append_pair("code_challenge_method", "S256");
+
+    let (sender, receiver) = oneshot::channel();
+    if state
+        .zoom_sign_in
+        .lock()
+        .await
+        .replace(PendingZoomSignIn {
+            state: csrf_state,
+            sender,
+        })
+        .is_some()
+    {
+        println!("[Zoom] Replacing an unfinished sign-in.");
+    }
+
+    println!(
+        "[Zoom] Opening browser for sign-in at {}",
+        config.authorize_url
+    );
+    app.opener()
+        .open_url(authorize_url.as_str(), None::<&str>)
+        .map_err(|err| format!("Failed to open the browser for Zoom sign-in: {err}"))?;
+
+    let code = match timeout(SIGN_IN_TIMEOUT, receiver).await {
+        Ok(Ok(result)) => result?,
+        Ok(Err(_)) => return Err("Zoom sign-in was cancelled.".to_string()),
+        Err(_) => {
+            state.zoom_sign_in.lock().await.take();
+            return Err(format!(
+                "Timed out after {} seconds waiting for Zoom sign-in to finish in the browser.",
+                SIGN_IN_TIMEOUT.as_secs()
+            ));
+        }
+    };
+
+    let client = reqwest::Client::new();
+    let token = request_token(
+        &client,
+        config,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("code_verifier", verifier.as_str()),
+        ],
+    )
+    .await
+    .map_err(|err| format!("Failed to exchange the Zoom authorization code: {err}"))?;
+
+    let response = client
+        .get(&config.user_url)
+        .timeout(REQUEST_TIMEOUT)
+        .bearer_auth(&token.access_token)
+        .send()
+        .await
+        .map_err(|err| format!("Failed to look up the Zoom user: {err}"))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Zoom user lookup failed with status {}.",
+            response.status()
+        ));
+    }
+    let user: ZoomUserResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("Failed to parse the Zoom user profile: {err}"))?;
+
+    let display_name = user.display_name.or_else(|| {
+        let full = format!(
+            "{} {}",
+            user.first_name.unwrap_or_default(),
+            user.last_name.unwrap_or_default()
+        );
+        Some(full.trim().to_string()).filter(|name| !name.is_empty())
+    });
+    println!("[Zoom] Signed in as Zoom user {}.", user.id);
+
+    Ok(ZoomSession {
+        account: ZoomAccount {
+            user_id: user.id,
+            display_name,
+            email: user.email,
+        },
+        expires_at: token.expires_at(),
+        access_token: token.access_token,
+        refresh_token: token.refresh_token,
+    })
+}
+
+async fn request_token(
+    client: &reqwest::Client,
+    config: &ZoomOAuthConfig,
+    form: &[(&str, &str)],
+) -> Result<TokenResponse, String> {
+    let mut request = client
+        .post(&config.token_url)
+        .timeout(REQUEST_TIMEOUT)
+        .form(&[form, &[("client_id", config.client_id.as_str())]].concat());
+    if let Some(secret) = &config.client_secret {
+        request = request.basic_auth(&config.client_id, Some(secret));
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|err| format!("failed to reach the Zoom token endpoint: {err}"))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("{status}: {body}"));
+    }
+    response
+        .json()
+        .await
+        .map_err(|err| format!("failed to parse the Zoom token response: {err}"))
+}
+
+/// Returns an access token for the signed-in Zoom account, refreshing it first when it is
+/// about to expire. `None` when nobody is signed in. If the refresh fails the session is
+/// dropped, since the Hub would reject the old token anyway.
+pub(crate) async fn access_token(
+    app: &AppHandle,
+    state: &AppState,
+) -> Result<Option<String>, String> {
+    let mut guard = state.zoom_session.lock().await;
+    let Some(session) = guard.as_mut() else {
+        return Ok(None);
+    };
+    if session.expires_at > Instant::now() + TOKEN_REFRESH_MARGIN {
+        return Ok(Some(session.access_token.clone()));
+    }
+
+    let refreshed = match session.refresh_token.clone() {
+        Some(refresh_token) => {
+            match load_settings(app).and_then(|settings| settings.zoom_oauth()) {
+                Ok(config) => request_token(
+                    &reqwest::Client::new(),
+                    &config,
+                    &[
+                        ("grant_type", "refresh_token"),
+                        ("refresh_token", refresh_token.as_str()),
+                    ],
+                )
+                .await
+                .map_err(|err| format!("Zoom did not renew it ({err})")),
+                Err(err) => Err(format!("it could not be renewed ({err})")),
+            }
+        }
+        None => Err("Zoom did not issue a refresh token".to_string()),
+    };
+
+    match refreshed {
+        Ok(token) => {
+            session.update_tokens(token);
+            println!(
+                "[Zoom] Refreshed the access token for Zoom user {}.",
+                session.account.user_id
+            );
+            Ok(Some(session.access_token.clone()))
+        }
+        Err(err) => {
+            guard.take();
+            Err(format!(
+                "Your Zoom sign-in has expired and {err}. Sign in with Zoom again."
+            ))
+        }
+    }
+}
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import {
   FilesetResolver,
   HandLandmarker,
@@ -14,6 +15,96 @@ type BackgroundImageResult = {
   dataUrl: string;
 };
 
+type ImagePullProgress = {
+  image: string;
+  status: string;
+  currentBytes: number;
+  totalBytes: number;
+  done: boolean;
+};
+
+type ModelDownloadProgress = {
+  model: string;
+  status:
+    | "requested"
+    | "downloading"
+    | "stalled"
+    | "complete"
+    | "failed"
+    | "cancelled"
+    | "skipped";
+  pulledBytes: number;
+  totalBytes: number;
+  percent: number | null;
+  bytesPerSec: number | null;
+  etaSecs: number | null;
+  message: string;
+};
+
+type ModelRole = "transcription" | "prompt";
+
+type RoleSelection = {
+  role: ModelRole;
+  model: string | null;
+  candidates: string[];
+  downloaded: boolean;
+  skipped: { model: string; reason: string }[];
+};
+
+type CompanionLogEntry = {
+  level: "info" | "error";
+  message: string;
+};
+
+type HubStatus = {
+  connectivity: "idle" | "connected" | "degraded" | "lost";
+  detail: string | null;
+  lastSuccessMs: number | null;
+};
+
+type AgentStatus = {
+  running: boolean;
+  tunnelUrl: string | null;
+  hub: {
+    baseUrl: string;
+    registerPath: string;
+    unregisterPath: string;
+    heartbeatPath: string;
+    releaseIdentityPath: string;
+  } | null;
+  hubStatus: HubStatus;
+  identity: string;
+  zoomAccount: ZoomAccount | null;
+};
+
+type ScreenNameIdentity = {
+  display: string;
+  normalized: string;
+};
+
+type ZoomAccount = {
+  userId: string;
+  displayName: string | null;
+  email: string | null;
+};
+
+type OrphanedContainer = {
+  id: string;
+  name: string;
+  instanceId: string | null;
+  startedAt: number | null;
+  state: string;
+  error: string | null;
+};
+
+type TunnelStatus =
+  | { kind: "urlAssigned"; url: string }
+  | { kind: "connected" }
+  | { kind: "disconnected"; detail: string }
+  | { kind: "rateLimited"; detail: string }
+  | { kind: "error"; detail: string }
+  | { kind: "exited" };
+
 type BackgroundPromptDecision =
   | {
       status: "generate";
@@ -38,13 +129,30 @@ let lastBackgroundPrompt: string | null = null;
 let preflightScreenEl: HTMLElement | null;
 let appContentEl: HTMLElement | null;
 let preflightStatusEl: HTMLElement | null;
+let preflightInferenceTitleEl: HTMLElement | null;
 let preflightPermissionsBtnEl: HTMLButtonElement | null;
 let preflightRetryBtnEl: HTMLButtonElement | null;
+let preflightCancelDownloadsBtnEl: HTMLButtonElement | null;
+let modelDownloadsEl: HTMLElement | null;
 let screenNamePreviewEl: HTMLElement | null;
 let wheelsContainerEl: HTMLElement | null;
 let gestureStatusEl: HTMLElement | null;
 let activeWheelStatusEl: HTMLElement | null;
 let logContainerEl: HTMLElement | null;
+let zoomSignInBtnEl: HTMLButtonElement | null;
+let zoomAccountStatusEl: HTMLElement | null;
+let zoomAccount: ZoomAccount | null = null;
+let profileSelectEl: HTMLSelectElement | null;
+let settingsFormEl: HTMLFormElement | null;
+let settingsFormStatusEl: HTMLElement | null;
+let nanobananaKeyInputEl: HTMLInputElement | null;
+let nanobananaKeyStatusEl: HTMLElement | null;
+let bundlePassphraseEl: HTMLInputElement | null;
+let bundleStatusEl: HTMLElement | null;
+let importChangesEl: HTMLElement | null;
+let importApplyBtnEl: HTMLButtonElement | null;
+let pendingImportBundle: string | null = null;
+let configEntriesEl: HTMLElement | null;
 
 let cameraStream: MediaStream | null = null;
 
@@ -86,26 +194,138 @@ const TRANSCRIPTION_SAMPLE_DURATION_MS = 12_000;
 const TRANSCRIPTION_SAMPLE_RATE = 16_000;
 const TRANSCRIPTION_PROMPT =
   "Transcribe the provided audio sample verbatim, including filler words when they are audible.";
-const TRANSCRIPTION_ENDPOINT =
-  "http://localhost:12434/engines/llama.cpp/v1/chat/completions";
-const DMR_BASE_URL = "http://localhost:12434";
 let TRANSCRIPTION_MODEL = "";
 let BACKGROUND_PROMPT_MODEL = "";
+let BACKGROUND_PROMPT_STYLE = "";
+
+type Profile = {
+  screenName?: string | null;
+  modelTranscription?: string | null;
+  modelPrompt?: string | null;
+  modelTranscriptionFallbacks?: string[];
+  modelPromptFallbacks?: string[];
+  backgroundStyle?: string | null;
+};
 
 type Settings = {
-  modelTranscription?: string;
-  modelPrompt?: string;
+  activeProfile: string;
+  profiles: Record<string, Profile>;
+  hubUrl?: string | null;
+  hubHeartbeatIntervalSecs?: number | null;
+  inferenceBackend?: InferenceBackend;
+  inferenceBaseUrl?: string | null;
+};
+
+type InferenceBackend = "dmr" | "llamaCpp" | "ollama" | "openAiCompatible";
+
+type InferenceBackendInfo = {
+  backend: InferenceBackend;
+  name: string;
+  baseUrl: string;
+  canPull: boolean;
+};
+
+const SETTINGS_FORM_FIELDS = [
+  "modelTranscription",
+  "modelTranscriptionFallbacks",
+  "modelPrompt",
+  "modelPromptFallbacks",
+  "backgroundStyle",
+  "inferenceBackend",
+  "inferenceBaseUrl",
+  "hubUrl",
+  "hubHeartbeatIntervalSecs",
+] as const;
+type SettingsFormField = (typeof SETTINGS_FORM_FIELDS)[number];
+
+type SettingsUpdateError = {
+  message: string;
+  fieldErrors: { field: string; message: string }[];
+};
+
+type ImportPreview = {
+  changes: { path: string; current: unknown; incoming: unknown }[];
+  secrets: string[];
+  secretsSkipped: boolean;
+};
+
+type ConfigReport = {
+  entries: {
+    key: string;
+    value: unknown;
+    source: string;
+    origin?: string | null;
+    profile?: string | null;
+  }[];
+  warnings: string[];
+  systemSettingsPath?: string | null;
+};
+
+let currentSettings: Settings | null = null;
+let modelSelection: RoleSelection[] = [];
+
+type ProfileSummary = {
+  name: string;
+  screenName: string | null;
+  active: boolean;
 };
 
+function applySettings(settings: Settings) {
+  currentSettings = settings;
+  fillSettingsForm(settings);
+  const profile = settings.profiles[settings.activeProfile] ?? {};
+  applyModelSelection();
+  BACKGROUND_PROMPT_STYLE = profile.backgroundStyle?.trim() ?? "";
+}
+
+function chooseModel(
+  role: ModelRole,
+  configured: string | null | undefined,
+  current: string,
+): string {
+  // The last check's pick stands until the configured model changes.
+  const selection = modelSelection.find((entry) => entry.role === role);
+  if (selection && (!configured || selection.candidates[0] === configured)) {
+    return selection.model ?? "";
+  }
+  return configured || current;
+}
+
+function applyModelSelection() {
+  const profile = currentSettings?.profiles[currentSettings.activeProfile] ?? {};
+  TRANSCRIPTION_MODEL = chooseModel(
+    "transcription",
+    profile.modelTranscription,
+    TRANSCRIPTION_MODEL,
+  );
+  BACKGROUND_PROMPT_MODEL = chooseModel(
+    "prompt",
+    profile.modelPrompt,
+    BACKGROUND_PROMPT_MODEL,
+  );
+}
+
+function describeRoleSelection(selection: RoleSelection): string {
+  const role =
+    selection.role === "transcription" ? "Transcription" : "Prompt";
+  const skipped = selection.skipped
+    .map((entry) => `${entry.model} (${entry.reason})`)
+    .join(", ");
+  if (!selection.model && selection.candidates.length === 0) {
+    return `No ${role.toLowerCase()} model is set, and this inference backend has no default. Choose one in Settings.`;
+  }
+  if (!selection.model) {
+    return `No ${role.toLowerCase()} model is available: ${skipped}.`;
+  }
+  const source = selection.downloaded ? "downloaded" : "already pulled";
+  return skipped
+    ? `${role}: ${selection.model} (${source}), after skipping ${skipped}.`
+    : `${role}: ${selection.model} (${source}).`;
+}
+
 async function loadSettings() {
   try {
-    const settings = await invoke<Settings>("get_settings");
-    if (settings.modelTranscription) {
-      TRANSCRIPTION_MODEL = settings.modelTranscription;
-    }
-    if (settings.modelPrompt) {
-      BACKGROUND_PROMPT_MODEL = settings.modelPrompt;
-    }
+    applySettings(await invoke<Settings>("get_settings"));
   } catch (error) {
     console.error("Failed to load settings", error);
   }
@@ -115,8 +335,6 @@ const AUTO_TRANSCRIPTION_RETRY_DELAY_MS = 1_000;
 const AUTO_TRANSCRIPTION_FALLBACK_DELAY_MS = 1_000;
 const AUTO_TRANSCRIPTION_POST_IMAGE_DELAY_MS = 300;
 
-const BACKGROUND_PROMPT_ENDPOINT =
-  "http://localhost:12434/engines/llama.cpp/v1/chat/completions";
 const BACKGROUND_PROMPT_SYSTEM_PROMPT =
   "You are a helpful assistant that writes vivid prompts for image generation models.";
 const BACKGROUND_PROMPT_INSTRUCTIONS = [
@@ -168,7 +386,7 @@ let autoTranscriptionTimer: number | null = null;
 let autoAwaitingBackgroundImage = false;
 let wheelsLocked = false;
 
-const PREFLIGHT_KEYS = ["docker", "dmr", "models", "permissions"] as const;
+const PREFLIGHT_KEYS = ["docker", "inference", "models", "permissions"] as const;
 type PreflightKey = (typeof PREFLIGHT_KEYS)[number];
 type PreflightState = "pending" | "running" | "waiting" | "success" | "error";
 
@@ -188,14 +406,14 @@ type PreflightElements = {
 
 const preflightElements: Record<PreflightKey, PreflightElements> = {
   docker: { row: null, status: null, message: null },
-  dmr: { row: null, status: null, message: null },
+  inference: { row: null, status: null, message: null },
   models: { row: null, status: null, message: null },
   permissions: { row: null, status: null, message: null },
 };
 
 let preflightState: Record<PreflightKey, PreflightState> = {
   docker: "pending",
-  dmr: "pending",
+  inference: "pending",
   models: "pending",
   permissions: "pending",
 };
@@ -240,6 +458,14 @@ function formatError(error: unknown): string {
   if (typeof error === "string") {
     return error;
   }
+  if (
+    error &&
+    typeof error === "object" &&
+    "message" in error &&
+    typeof error.message === "string"
+  ) {
+    return error.message;
+  }
   if (error && typeof error === "object" && "name" in error) {
     return String((error as { name: string }).name);
   }
@@ -329,7 +555,7 @@ function setPreflightStatusText(text: string) {
 function resetPreflightState() {
   preflightState = {
     docker: "pending",
-    dmr: "pending",
+    inference: "pending",
     models: "pending",
     permissions: "pending",
   };
@@ -342,6 +568,9 @@ function resetPreflightState() {
   if (preflightRetryBtnEl) {
     preflightRetryBtnEl.classList.add("hidden");
   }
+  if (modelDownloadsEl) {
+    modelDownloadsEl.replaceChildren();
+  }
   setPreflightStatusText("");
 }
 
@@ -390,6 +619,208 @@ async function hasGrantedMediaPermissions(): Promise<boolean> {
   return false;
 }
 
+function formatBytes(bytes: number): string {
+  if (bytes >= 1024 * 1024) {
+    return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+  }
+  if (bytes >= 1024) {
+    return `${(bytes / 1024).toFixed(1)} KB`;
+  }
+  return `${bytes} B`;
+}
+
+function formatEta(seconds: number): string {
+  if (seconds >= 3600) {
+    return `${Math.floor(seconds / 3600)}h ${Math.floor((seconds % 3600) / 60)}m`;
+  }
+  if (seconds >= 60) {
+    return `${Math.floor(seconds / 60)}m ${seconds % 60}s`;
+  }
+  return `${seconds}s`;
+}
+
+function describeModelDownload(progress: ModelDownloadProgress): string {
+  switch (progress.status) {
+    case "requested":
+      return "Requesting download…";
+    case "complete":
+      return "Downloaded.";
+    case "failed":
+      return `Failed: ${progress.message}`;
+    case "cancelled":
+      return "Cancelled.";
+    case "skipped":
+      return `Skipped: ${progress.message}`;
+  }
+  const parts = [
+    progress.totalBytes > 0
+      ? `${formatBytes(progress.pulledBytes)} of ${formatBytes(progress.totalBytes)}`
+      : formatBytes(progress.pulledBytes),
+  ];
+  if (progress.percent !== null) {
+    parts.push(`${Math.floor(progress.percent)}%`);
+  }
+  if (progress.bytesPerSec !== null) {
+    parts.push(`${formatBytes(progress.bytesPerSec)}/s`);
+  }
+  if (progress.etaSecs !== null) {
+    parts.push(`about ${formatEta(progress.etaSecs)} left`);
+  }
+  const summary = parts.join(", ");
+  return progress.status === "stalled"
+    ? `Stalled at ${summary}. Waiting for data…`
+    : summary;
+}
+
+function renderModelDownloads(downloads: Map<string, ModelDownloadProgress>) {
+  if (!modelDownloadsEl) {
+    return;
+  }
+  modelDownloadsEl.replaceChildren(
+    ...Array.from(downloads.values(), (progress) => {
+      const item = document.createElement("li");
+      item.textContent = `${progress.model}: ${describeModelDownload(progress)}`;
+      if (progress.status === "downloading" || progress.status === "stalled") {
+        const skip = document.createElement("button");
+        skip.type = "button";
+        skip.className = "secondary-btn";
+        skip.textContent = "Skip";
+        skip.addEventListener("click", () => {
+          void handleCancelModelDownloads(progress.model);
+        });
+        item.append(" ", skip);
+      }
+      return item;
+    }),
+  );
+}
+
+async function ensureRequiredModels(): Promise<RoleSelection[]> {
+  const downloads = new Map<string, ModelDownloadProgress>();
+  const unlisten = await listen<ModelDownloadProgress>(
+    "model-download-progress",
+    (event) => {
+      const progress = event.payload;
+      const previous = downloads.get(progress.model);
+      downloads.set(progress.model, progress);
+      renderModelDownloads(downloads);
+      if (previous?.status !== progress.status) {
+        logEvent(
+          `[Preflight] ${progress.model}: ${describeModelDownload(progress)}`,
+          progress.status === "failed" ? "error" : "info",
+        );
+      }
+      if (progress.status === "downloading" || progress.status === "stalled") {
+        setPreflightState(
+          "models",
+          "waiting",
+          undefined,
+          progress.status === "stalled" ? "Download stalled" : "Downloading…",
+        );
+      }
+    },
+  );
+
+  preflightCancelDownloadsBtnEl?.classList.remove("hidden");
+  try {
+    return await invoke<RoleSelection[]>("ensure_models_ready");
+  } finally {
+    unlisten();
+    preflightCancelDownloadsBtnEl?.classList.add("hidden");
+  }
+}
+
+async function handleCancelModelDownloads(model?: string) {
+  try {
+    const cancelled = await invoke<string[]>("cancel_model_download", {
+      model: model ?? null,
+    });
+    if (cancelled.length) {
+      logEvent(`[Preflight] Cancelling downloads: ${cancelled.join(", ")}`);
+    }
+  } catch (error) {
+    logEvent(
+      `[Preflight] Failed to cancel model downloads: ${formatError(error)}`,
+      "error",
+    );
+  }
+}
+
+async function prepareContainerImages() {
+  let lastLoggedPercent = -1;
+  const unlisten = await listen<ImagePullProgress>(
+    "image-pull-progress",
+    (event) => {
+      const progress = event.payload;
+      if (progress.done) {
+        logEvent(`[Preflight] ${progress.image}: ${progress.status}.`);
+        return;
+      }
+      if (progress.totalBytes <= 0) {
+        return;
+      }
+      const percent = Math.floor(
+        (progress.currentBytes / progress.totalBytes) * 100,
+      );
+      setPreflightState(
+        "docker",
+        "running",
+        `Pulling ${progress.image}: ${formatBytes(progress.currentBytes)} of ${formatBytes(progress.totalBytes)} (${percent}%)`,
+        "Pulling image…",
+      );
+      if (percent >= lastLoggedPercent + 25) {
+        lastLoggedPercent = percent;
+        logEvent(`[Preflight] Pulling ${progress.image}: ${percent}%`);
+      }
+    },
+  );
+
+  try {
+    await invoke("prepare_images");
+  } finally {
+    unlisten();
+  }
+}
+
+async function cleanupOrphanedContainers() {
+  try {
+    const orphans = await invoke<OrphanedContainer[]>(
+      "cleanup_orphaned_containers",
+    );
+    if (orphans.length === 0) {
+      logEvent("[Preflight] No containers left behind by previous runs.");
+      return;
+    }
+    const removed = orphans.filter((container) => !container.error);
+    if (removed.length > 0) {
+      const summary = removed
+        .map((container) => {
+          const started = container.startedAt
+            ? ` started ${new Date(container.startedAt * 1000).toLocaleString()}`
+            : "";
+          return `${container.name} (${container.state || "unknown"}${started})`;
+        })
+        .join(", ");
+      logEvent(
+        `[Preflight] Removed ${removed.length} container(s) left behind by previous runs: ${summary}`,
+      );
+    }
+    for (const container of orphans) {
+      if (container.error) {
+        logEvent(
+          `[Preflight] Could not remove container ${container.name} left behind by a previous run: ${container.error}`,
+          "error",
+        );
+      }
+    }
+  } catch (error) {
+    logEvent(
+      `[Preflight] Failed to clean up containers from previous runs: ${formatError(error)}`,
+      "error",
+    );
+  }
+}
+
 async function runPreflightChecks() {
   if (preflightCompleted || preflightInProgress) {
     return;
@@ -406,6 +837,7 @@ async function runPreflightChecks() {
   );
   logEvent("[Preflight] Checking Docker Desktop…");
   try {
+    await prepareContainerImages();
     await invoke("check_docker_access");
     setPreflightState(
       "docker",
@@ -414,6 +846,7 @@ async function runPreflightChecks() {
       "Ready",
     );
     logEvent("[Preflight] Docker Desktop check passed.");
+    await cleanupOrphanedContainers();
   } catch (error) {
     const message = formatError(error);
     setPreflightState(
@@ -432,59 +865,67 @@ async function runPreflightChecks() {
     return;
   }
 
+  let backend: InferenceBackendInfo;
+  try {
+    backend = await invoke<InferenceBackendInfo>("get_inference_backend");
+  } catch (error) {
+    const message = formatError(error);
+    setPreflightState(
+      "inference",
+      "error",
+      `The inference backend settings are invalid: ${message}`,
+      "Misconfigured",
+    );
+    logEvent(
+      `[Preflight] Inference backend settings are invalid: ${message}`,
+      "error",
+    );
+    showPreflightFailure("Fix the inference backend settings, then retry.");
+    return;
+  }
+  const backendName =
+    backend.name.charAt(0).toUpperCase() + backend.name.slice(1);
+  setTextContent(preflightInferenceTitleEl, backendName);
   setPreflightState(
-    "dmr",
+    "inference",
     "running",
-    "Contacting Docker Model Runner at http://localhost:12434…",
+    `Contacting ${backend.name} at ${backend.baseUrl}…`,
   );
-  setPreflightStatusText("Checking Docker Model Runner on http://localhost:12434…");
-  logEvent("[Preflight] Checking Docker Model Runner…");
+  setPreflightStatusText(`Checking ${backend.name} on ${backend.baseUrl}…`);
+  logEvent(`[Preflight] Checking ${backend.name}…`);
   try {
-    const modelsEndpoint = `${DMR_BASE_URL}/engines/llama.cpp/v1/models`;
-    const response = await fetch(modelsEndpoint);
-    if (!response.ok) {
-      throw new Error(`HTTP ${response.status}`);
-    }
-    const bodyText = await response.text();
-    let summary = bodyText.trim();
-    try {
-      const parsed = JSON.parse(bodyText) as { data?: Array<{ id?: string }> };
-      if (Array.isArray(parsed.data) && parsed.data.length > 0) {
-        const ids = parsed.data
-          .map((entry) => (entry && typeof entry.id === "string" ? entry.id : "unknown"))
-          .slice(0, 3)
-          .join(", ");
-        summary = `Models available: ${ids}${
-          parsed.data.length > 3 ? ` (+${parsed.data.length - 3} more)` : ""
-        }`;
-      } else if (parsed && typeof parsed === "object") {
-        summary = "Docker Model Runner responded with JSON.";
-      }
-    } catch {
-      // Ignore parse failure; fall back to raw text snippet below.
-    }
-    const snippet = summary.length > 160 ? `${summary.slice(0, 160)}…` : summary || "OK";
+    const models = await invoke<{ tags: string[] }[]>("list_models");
+    const ids = models
+      .map((model) => model.tags[0] ?? "untagged")
+      .slice(0, 3)
+      .join(", ");
+    const summary = models.length
+      ? `Models available: ${ids}${models.length > 3 ? ` (+${models.length - 3} more)` : ""}`
+      : "No models available yet.";
+    const snippet = summary.length > 160 ? `${summary.slice(0, 160)}…` : summary;
     setPreflightState(
-      "dmr",
+      "inference",
       "success",
-      `Docker Model Runner responded: ${snippet}`,
+      `${backendName} responded: ${snippet}`,
       "Ready",
     );
-    logEvent("[Preflight] Docker Model Runner responded successfully.");
+    logEvent(`[Preflight] ${backendName} responded successfully.`);
   } catch (error) {
     const message = formatError(error);
+    // Docker Model Runner is the one backend the user enables through Docker Desktop.
+    const hint =
+      backend.backend === "dmr"
+        ? " Enable Docker Model Runner following https://docs.docker.com/ai/model-runner/get-started/#enable-docker-model-runner."
+        : "";
     setPreflightState(
-      "dmr",
-      "error",
-      `Could not reach Docker Model Runner at ${DMR_BASE_URL}/engines/llama.cpp/v1/models. Enable Docker Model Runner following https://docs.docker.com/ai/model-runner/get-started/#enable-docker-model-runner. Details: ${message}`,
-      "DMR offline",
-    );
-    logEvent(
-      `[Preflight] Docker Model Runner check failed: ${message}`,
+      "inference",
       "error",
+      `Could not reach ${backend.name} at ${backend.baseUrl}.${hint} Details: ${message}`,
+      "Backend offline",
     );
+    logEvent(`[Preflight] ${backendName} check failed: ${message}`, "error");
     showPreflightFailure(
-      "Enable Docker Model Runner and wait for http://localhost:12434/engines/llama.cpp/v1/models to respond (see docs).",
+      `Start ${backend.name} and wait for ${backend.baseUrl} to respond.`,
     );
     return;
   }
@@ -496,32 +937,36 @@ async function runPreflightChecks() {
     "Ensuring models…",
   );
   setPreflightStatusText(
-    "Ensuring Docker Model Runner has downloaded the required models. This may take several minutes the first time.",
+    backend.canPull
+      ? `Ensuring ${backend.name} has downloaded the required models. This may take several minutes the first time.`
+      : `Checking which of the required models ${backend.name} serves…`,
   );
   logEvent("[Preflight] Ensuring required models are available…");
   try {
-    await invoke("ensure_models_ready");
-    setPreflightState(
-      "models",
-      "success",
-      "All required Docker Model Runner models are ready.",
-      "Ready",
-    );
-    logEvent("[Preflight] Required models confirmed.");
+    const selections = await ensureRequiredModels();
+    const summary = selections.map(describeRoleSelection).join(" ");
+    if (selections.every((selection) => selection.model)) {
+      setPreflightState("models", "success", summary, "Ready");
+      logEvent(`[Preflight] Required models confirmed. ${summary}`);
+    } else {
+      // Carry on without the missing role; the features that need it report it when used.
+      setPreflightState("models", "error", summary, "Some models missing");
+      logEvent(`[Preflight] ${summary}`, "error");
+    }
   } catch (error) {
     const message = formatError(error);
     setPreflightState(
       "models",
       "error",
-      `Docker Model Runner is still downloading models or returned an error. Leave it running and retry once downloads finish. Details: ${message}`,
-      "Downloading models",
+      `The required models could not be downloaded. Check that ${backend.name} is running and this machine is online, then retry. Details: ${message}`,
+      "Models unavailable",
     );
     logEvent(
       `[Preflight] Model availability check failed: ${message}`,
       "error",
     );
     showPreflightFailure(
-      "Waiting for Docker Model Runner to finish downloading models. Leave it running and retry.",
+      "Model downloads did not finish. Retry to start them again.",
     );
     return;
   }
@@ -654,16 +1099,42 @@ function getScreenNameFromWheels() {
   return trimmed.replace(/\s+/g, " ");
 }
 
+let screenNamePreviewRequest = 0;
+
 function updateScreenNamePreview() {
   if (!screenNamePreviewEl) {
     return;
   }
   const value = getScreenNameFromWheels();
-  if (value) {
-    setTextContent(screenNamePreviewEl, `Screen name: ${value}`);
-  } else {
+  if (!value) {
     setTextContent(screenNamePreviewEl, "Screen name: (blank)");
+    return;
   }
+
+  setTextContent(screenNamePreviewEl, `Screen name: ${value}`);
+  const request = ++screenNamePreviewRequest;
+  invoke<ScreenNameIdentity>("preview_screen_name", { screenName: value })
+    .then((identity) => {
+      if (request !== screenNamePreviewRequest) {
+        return;
+      }
+      const suffix =
+        identity.normalized === identity.display
+          ? ""
+          : ` (registers as "${identity.normalized}")`;
+      setTextContent(
+        screenNamePreviewEl,
+        `Screen name: ${identity.display}${suffix}`,
+      );
+    })
+    .catch((error) => {
+      if (request === screenNamePreviewRequest) {
+        setTextContent(
+          screenNamePreviewEl,
+          `Screen name: ${value} – ${formatError(error)}`,
+        );
+      }
+    });
 }
 
 function updateActiveWheelStatus() {
@@ -1061,6 +1532,29 @@ async function prepareTranscriptionPayload(
   };
 }
 
+type ChatCompletion = {
+  model: string;
+  choices: { message: { content?: string | null } }[];
+  usage?: {
+    prompt_tokens: number;
+    completion_tokens: number;
+    total_tokens: number;
+  } | null;
+};
+
+type ChatDelta = {
+  streamId: string;
+  index: number;
+  content: string;
+};
+
+function describeUsage(completion: ChatCompletion): string {
+  const usage = completion.usage;
+  return usage
+    ? ` (${usage.prompt_tokens} prompt + ${usage.completion_tokens} completion tokens)`
+    : "";
+}
+
 function extractCompletionText(payload: unknown): string {
   if (!payload || typeof payload !== "object") {
     return "";
@@ -1189,6 +1683,15 @@ async function triggerTranscriptionCapture() {
     return;
   }
 
+  if (!TRANSCRIPTION_MODEL) {
+    setTextContent(micStatusEl, "No transcription model is available.");
+    logEvent(
+      "[Transcription] No transcription model is available. Add a fallback in Settings or pull one with `docker model pull`, then rerun the checks.",
+      "error",
+    );
+    return;
+  }
+
   autoAwaitingBackgroundImage = false;
   isTranscriptionInProgress = true;
 
@@ -1265,43 +1768,25 @@ async function triggerTranscriptionCapture() {
     );
 
     const requestStartedAt = performance.now();
-    const response = await fetch(TRANSCRIPTION_ENDPOINT, {
-      method: "POST",
-      headers: {
-        "Content-Type": "application/json",
-      },
-      body: payloadJson,
+    const streamId = `transcription-${Date.now()}`;
+    let partialTranscript = "";
+    const unlisten = await listen<ChatDelta>("chat-delta", (event) => {
+      if (event.payload.streamId !== streamId) {
+        return;
+      }
+      partialTranscript += event.payload.content;
+      setTextContent(micStatusEl, `Transcribing: ${partialTranscript}`);
     });
+    const completion = await invoke<ChatCompletion>("chat_completion", {
+      request: payload,
+      streamId,
+    }).finally(unlisten);
     const latencyMs = performance.now() - requestStartedAt;
     logEvent(
-      `[Transcription] Transcription request completed in ${latencyMs.toFixed(0)} ms with status ${response.status}.`,
+      `[Transcription] Transcription request completed in ${latencyMs.toFixed(0)} ms${describeUsage(completion)}.`,
     );
-
-    const responseText = await response.text();
-    logEvent("[Transcription] Received raw response payload.");
-
-    if (!response.ok) {
-      throw new Error(
-        `Transcription request failed: HTTP ${response.status}`,
-      );
-    }
-
-    let transcript = "";
-    try {
-      const parsed = JSON.parse(responseText) as unknown;
-      transcript = extractCompletionText(parsed);
-      logEvent("[Transcription] Parsed response JSON.");
-      console.log("[Transcription] Parsed response:", parsed);
-    } catch (parseError) {
-      console.warn(
-        "Failed to parse transcription response as JSON.",
-        parseError,
-      );
-      logEvent(
-        "[Transcription] Failed to parse response JSON. Check developer console for details.",
-        "error",
-      );
-    }
+    console.log("[Transcription] Completion:", completion);
+    const transcript = extractCompletionText(completion);
 
     if (transcript) {
       logEvent(`[Transcription] Transcript: "${transcript}"`);
@@ -1389,9 +1874,15 @@ async function generateVirtualBackgroundPrompt(
   transcript: string,
   lastPrompt: string | null,
 ): Promise<BackgroundPromptDecision> {
+  const instructions = BACKGROUND_PROMPT_STYLE
+    ? `Always describe the scene in this style: ${BACKGROUND_PROMPT_STYLE}.\n${BACKGROUND_PROMPT_INSTRUCTIONS}`
+    : BACKGROUND_PROMPT_INSTRUCTIONS;
+  if (!BACKGROUND_PROMPT_MODEL) {
+    throw new Error("No prompt model is available.");
+  }
   const userMessage = lastPrompt
-    ? `${BACKGROUND_PROMPT_INSTRUCTIONS}\nPrevious prompt: ${lastPrompt}\n\nTranscript: ${transcript}`
-    : `${BACKGROUND_PROMPT_INSTRUCTIONS}\n${transcript}`;
+    ? `${instructions}\nPrevious prompt: ${lastPrompt}\n\nTranscript: ${transcript}`
+    : `${instructions}\n${transcript}`;
   const payload = {
     model: BACKGROUND_PROMPT_MODEL,
     messages: [
@@ -1411,42 +1902,15 @@ async function generateVirtualBackgroundPrompt(
   logEvent(`[Background] Payload JSON bytes=${payloadBytes}.`);
 
   const requestStartedAt = performance.now();
-  const response = await fetch(BACKGROUND_PROMPT_ENDPOINT, {
-    method: "POST",
-    headers: {
-      "Content-Type": "application/json",
-    },
-    body: payloadJson,
+  const response = await invoke<ChatCompletion>("chat_completion", {
+    request: payload,
   });
   const latencyMs = performance.now() - requestStartedAt;
   logEvent(
-    `[Background] Prompt request completed in ${latencyMs.toFixed(0)} ms with status ${response.status}.`,
+    `[Background] Prompt request completed in ${latencyMs.toFixed(0)} ms${describeUsage(response)}.`,
   );
 
-  const responseText = await response.text();
-  logEvent("[Background] Received raw prompt response payload.");
-
-  if (!response.ok) {
-    throw new Error(
-      `Virtual background prompt request failed: HTTP ${response.status}`,
-    );
-  }
-
-  let parsed: unknown;
-  try {
-    parsed = JSON.parse(responseText) as unknown;
-    logEvent("[Background] Parsed prompt response JSON.");
-  } catch (parseError) {
-    logEvent(
-      "[Background] Failed to parse prompt response JSON.",
-      "error",
-    );
-    throw new Error(
-      `Virtual background prompt response parsing failed: ${formatError(parseError)}`,
-    );
-  }
-
-  const completion = extractCompletionText(parsed);
+  const completion = extractCompletionText(response);
   logEvent(`[Background] Model completion text: ${completion}`);
 
   return parseBackgroundPromptDecision(completion);
@@ -2125,18 +2589,25 @@ async function registerAgent() {
     logEvent("Agent start blocked: screen name is blank.", "error");
     return;
   }
-  if (screenName.length < 3) {
-    statusMsgEl.textContent = "Screen name must be at least 3 characters.";
-    logEvent(
-      "Agent start blocked: screen name shorter than 3 characters.",
-      "error",
-    );
+  let identity: ScreenNameIdentity;
+  try {
+    identity = await invoke<ScreenNameIdentity>("preview_screen_name", {
+      screenName,
+    });
+  } catch (error) {
+    statusMsgEl.textContent = formatError(error);
+    logEvent(`Agent start blocked: ${formatError(error)}`, "error");
     return;
   }
 
   agentTransitionInProgress = true;
-  setTextContent(statusMsgEl, `Starting agent for ${screenName}...`);
-  logEvent(`Starting agent for ${screenName}…`);
+  setTextContent(
+    statusMsgEl,
+    `Starting agent for ${identity.display} (Hub identity "${identity.normalized}")...`,
+  );
+  logEvent(
+    `Starting agent for ${identity.display} (Hub identity "${identity.normalized}")…`,
+  );
   setWheelsLocked(true);
 
   try {
@@ -2264,6 +2735,389 @@ function completePreflight() {
   logEvent("Preflight checks completed.");
 }
 
+async function registerBackendEventListeners() {
+  await listen<RoleSelection[]>("model-selection", (event) => {
+    modelSelection = event.payload;
+    applyModelSelection();
+  });
+  await listen<CompanionLogEntry>("companion-log", (event) => {
+    logEvent(event.payload.message, event.payload.level);
+  });
+  await listen<string>("settings-recovered", (event) => {
+    setTextContent(settingsFormStatusEl, event.payload);
+  });
+  await listen<Settings>("settings-changed", (event) => {
+    applySettings(event.payload);
+    void loadProfiles();
+  });
+  await listen<HubStatus>("hub-status", (event) => {
+    const status = event.payload;
+    const detail = status.detail ? `: ${status.detail}` : "";
+    switch (status.connectivity) {
+      case "connected":
+        logEvent(`[Hub] Registration active${detail}`);
+        break;
+      case "degraded":
+        logEvent(`[Hub] Hub unreachable, will retry${detail}`, "error");
+        break;
+      case "lost":
+        logEvent(`[Hub] Registration lost, will retry${detail}`, "error");
+        break;
+      case "idle":
+        break;
+    }
+  });
+  await listen<TunnelStatus>("tunnel-status", (event) => {
+    const status = event.payload;
+    switch (status.kind) {
+      case "urlAssigned":
+        logEvent(`[Tunnel] Public URL assigned: ${status.url}`);
+        break;
+      case "connected":
+        logEvent("[Tunnel] Connection to Cloudflare edge registered.");
+        break;
+      case "disconnected":
+        logEvent(`[Tunnel] Connection lost: ${status.detail}`, "error");
+        break;
+      case "rateLimited":
+        logEvent(`[Tunnel] Rate limited by Cloudflare: ${status.detail}`, "error");
+        break;
+      case "error":
+        logEvent(`[Tunnel] ${status.detail}`, "error");
+        break;
+      case "exited":
+        logEvent("[Tunnel] cloudflared container stopped.");
+        break;
+    }
+  });
+}
+
+function settingsFormValues(
+  settings: Settings,
+): Record<SettingsFormField, string> {
+  const profile = settings.profiles[settings.activeProfile] ?? {};
+  return {
+    modelTranscription: profile.modelTranscription ?? "",
+    modelTranscriptionFallbacks: (profile.modelTranscriptionFallbacks ?? []).join(
+      ", ",
+    ),
+    modelPrompt: profile.modelPrompt ?? "",
+    modelPromptFallbacks: (profile.modelPromptFallbacks ?? []).join(", "),
+    backgroundStyle: profile.backgroundStyle ?? "",
+    inferenceBackend: settings.inferenceBackend ?? "dmr",
+    inferenceBaseUrl: settings.inferenceBaseUrl ?? "",
+    hubUrl: settings.hubUrl ?? "",
+    hubHeartbeatIntervalSecs: settings.hubHeartbeatIntervalSecs?.toString() ?? "",
+  };
+}
+
+function settingsInput(
+  field: SettingsFormField,
+): HTMLInputElement | HTMLSelectElement | null {
+  return (
+    settingsFormEl?.querySelector<HTMLInputElement | HTMLSelectElement>(
+      `[name="${field}"]`,
+    ) ?? null
+  );
+}
+
+function showSettingsFieldError(field: string, message: string) {
+  const errorEl = settingsFormEl?.querySelector<HTMLElement>(
+    `[data-error-for="${field}"]`,
+  );
+  setTextContent(errorEl ?? null, message);
+}
+
+function fillSettingsForm(settings: Settings) {
+  // Leave the form alone while the user is editing it.
+  if (!settingsFormEl || settingsFormEl.contains(document.activeElement)) {
+    return;
+  }
+  const values = settingsFormValues(settings);
+  for (const field of SETTINGS_FORM_FIELDS) {
+    const input = settingsInput(field);
+    if (input) {
+      input.value = values[field];
+    }
+  }
+}
+
+async function handleSettingsSubmit() {
+  if (!settingsFormEl || !currentSettings) {
+    return;
+  }
+  const saved = settingsFormValues(currentSettings);
+  const patch: Record<string, string | string[] | number | null> = {};
+  for (const field of SETTINGS_FORM_FIELDS) {
+    showSettingsFieldError(field, "");
+    const value = settingsInput(field)?.value.trim() ?? "";
+    if (value === saved[field]) {
+      continue;
+    }
+    if (field === "hubHeartbeatIntervalSecs" && value) {
+      const secs = Number(value);
+      if (!Number.isInteger(secs)) {
+        showSettingsFieldError(field, "Enter a whole number of seconds.");
+        return;
+      }
+      patch[field] = secs;
+    } else if (
+      field === "modelTranscriptionFallbacks" ||
+      field === "modelPromptFallbacks"
+    ) {
+      patch[field] = value
+        .split(",")
+        .map((model) => model.trim())
+        .filter(Boolean);
+    } else {
+      patch[field] = value || null;
+    }
+  }
+  if (Object.keys(patch).length === 0) {
+    setTextContent(settingsFormStatusEl, "No changes to save.");
+    return;
+  }
+
+  setTextContent(settingsFormStatusEl, "Saving…");
+  try {
+    const settings = await invoke<Settings>("update_settings", { patch });
+    if (document.activeElement instanceof HTMLElement) {
+      document.activeElement.blur();
+    }
+    applySettings(settings);
+    setTextContent(settingsFormStatusEl, "Settings saved.");
+  } catch (error) {
+    const update = error as Partial<SettingsUpdateError>;
+    for (const fieldError of update.fieldErrors ?? []) {
+      showSettingsFieldError(fieldError.field, fieldError.message);
+    }
+    setTextContent(
+      settingsFormStatusEl,
+      update.message ?? formatError(error),
+    );
+  }
+}
+
+async function handleNanobananaKey(action: "save" | "test" | "clear") {
+  const apiKey = nanobananaKeyInputEl?.value.trim() ?? "";
+  try {
+    if (action === "save") {
+      if (!apiKey) {
+        setTextContent(nanobananaKeyStatusEl, "Enter a key to save.");
+        return;
+      }
+      await invoke("set_nanobanana_api_key", { apiKey });
+      if (nanobananaKeyInputEl) {
+        nanobananaKeyInputEl.value = "";
+      }
+      setTextContent(nanobananaKeyStatusEl, "Key saved to the secrets vault.");
+    } else if (action === "clear") {
+      await invoke("clear_nanobanana_api_key");
+      setTextContent(nanobananaKeyStatusEl, "Key removed from this profile.");
+    } else {
+      setTextContent(nanobananaKeyStatusEl, "Testing key…");
+      const result = await invoke<string>("test_nanobanana_api_key", {
+        apiKey: apiKey || null,
+      });
+      setTextContent(nanobananaKeyStatusEl, result);
+    }
+  } catch (error) {
+    setTextContent(nanobananaKeyStatusEl, formatError(error));
+  }
+}
+
+function bundlePassphrase(): string | null {
+  return bundlePassphraseEl?.value || null;
+}
+
+async function handleSettingsExport() {
+  setTextContent(bundleStatusEl, "Exporting…");
+  try {
+    const path = await invoke<string>("export_settings", {
+      secretsPassphrase: bundlePassphrase(),
+    });
+    setTextContent(bundleStatusEl, `Exported to ${path}`);
+  } catch (error) {
+    setTextContent(bundleStatusEl, formatError(error));
+  }
+}
+
+function renderImportPreview(preview: ImportPreview | null) {
+  const describe = (value: unknown) =>
+    value === null || value === undefined ? "—" : JSON.stringify(value);
+  const items = (preview?.changes ?? []).map((change) => {
+    const item = document.createElement("li");
+    item.textContent = `${change.path}: ${describe(change.current)} → ${describe(change.incoming)}`;
+    return item;
+  });
+  for (const secret of preview?.secrets ?? []) {
+    const item = document.createElement("li");
+    item.textContent = `Secret: ${secret}`;
+    items.push(item);
+  }
+  importChangesEl?.replaceChildren(...items);
+  if (importApplyBtnEl) {
+    importApplyBtnEl.hidden = items.length === 0;
+  }
+}
+
+async function handleSettingsImportFile(file: File) {
+  pendingImportBundle = null;
+  renderImportPreview(null);
+  try {
+    const bundle = await file.text();
+    const preview = await invoke<ImportPreview>("preview_settings_import", {
+      bundle,
+      passphrase: bundlePassphrase(),
+    });
+    renderImportPreview(preview);
+    const changeCount = preview.changes.length + preview.secrets.length;
+    if (changeCount === 0) {
+      setTextContent(bundleStatusEl, "The bundle matches your settings.");
+      return;
+    }
+    pendingImportBundle = bundle;
+    setTextContent(
+      bundleStatusEl,
+      preview.secretsSkipped
+        ? `${changeCount} changes. Enter the passphrase to include its secrets.`
+        : `${changeCount} changes. Review them, then apply.`,
+    );
+  } catch (error) {
+    setTextContent(bundleStatusEl, formatError(error));
+  }
+}
+
+async function handleSettingsImportApply() {
+  if (!pendingImportBundle) {
+    return;
+  }
+  try {
+    const preview = await invoke<ImportPreview>("import_settings", {
+      bundle: pendingImportBundle,
+      passphrase: bundlePassphrase(),
+    });
+    pendingImportBundle = null;
+    renderImportPreview(null);
+    await loadSettings();
+    await loadProfiles();
+    setTextContent(
+      bundleStatusEl,
+      `Imported ${preview.changes.length + preview.secrets.length} changes.`,
+    );
+  } catch (error) {
+    setTextContent(bundleStatusEl, formatError(error));
+  }
+}
+
+async function handleDescribeConfig() {
+  try {
+    const report = await invoke<ConfigReport>("describe_config");
+    const items = report.entries.map((entry) => {
+      const item = document.createElement("li");
+      const origin = entry.origin ? ` (${entry.origin})` : "";
+      item.textContent = `${entry.key}: ${JSON.stringify(entry.value)} ← ${entry.source}${origin}`;
+      return item;
+    });
+    for (const warning of report.warnings) {
+      const item = document.createElement("li");
+      item.className = "field-error";
+      item.textContent = warning;
+      items.push(item);
+    }
+    configEntriesEl?.replaceChildren(...items);
+  } catch (error) {
+    logEvent(`Failed to describe the configuration: ${formatError(error)}`, "error");
+  }
+}
+
+function renderProfiles(profiles: ProfileSummary[]) {
+  if (!profileSelectEl) {
+    return;
+  }
+  profileSelectEl.replaceChildren(
+    ...profiles.map((profile) => {
+      const option = document.createElement("option");
+      option.value = profile.name;
+      option.textContent = profile.screenName
+        ? `${profile.name} (${profile.screenName})`
+        : profile.name;
+      option.selected = profile.active;
+      return option;
+    }),
+  );
+}
+
+async function loadProfiles() {
+  try {
+    renderProfiles(await invoke<ProfileSummary[]>("list_profiles"));
+  } catch (error) {
+    logEvent(`Failed to load profiles: ${formatError(error)}`, "error");
+  }
+}
+
+async function handleProfileChange() {
+  if (!profileSelectEl) {
+    return;
+  }
+  const name = profileSelectEl.value;
+  try {
+    renderProfiles(
+      await invoke<ProfileSummary[]>("switch_profile", { name }),
+    );
+    await loadSettings();
+    await loadPersistedWheelState();
+    logEvent(`Switched to profile "${name}".`);
+  } catch (error) {
+    logEvent(`Profile switch failed: ${formatError(error)}`, "error");
+    await loadProfiles();
+  }
+}
+
+function describeZoomAccount(account: ZoomAccount): string {
+  const label = account.displayName || account.email || account.userId;
+  return `Signed in with Zoom as ${label}`;
+}
+
+function renderZoomAccount(account: ZoomAccount | null) {
+  zoomAccount = account;
+  setTextContent(
+    zoomAccountStatusEl,
+    account
+      ? describeZoomAccount(account)
+      : "Not signed in. Registration uses your screen name only.",
+  );
+  if (zoomSignInBtnEl) {
+    zoomSignInBtnEl.textContent = account
+      ? "Sign out of Zoom"
+      : "Sign in with Zoom";
+  }
+}
+
+async function handleZoomSignIn() {
+  if (!zoomSignInBtnEl) {
+    return;
+  }
+  zoomSignInBtnEl.disabled = true;
+  try {
+    if (zoomAccount) {
+      await invoke("sign_out_of_zoom");
+      renderZoomAccount(null);
+      logEvent("Signed out of Zoom.");
+    } else {
+      setTextContent(zoomAccountStatusEl, "Finish signing in in your browser…");
+      const account = await invoke<ZoomAccount>("sign_in_with_zoom");
+      renderZoomAccount(account);
+      logEvent(`${describeZoomAccount(account)}. The next start is verified.`);
+    }
+  } catch (error) {
+    renderZoomAccount(null);
+    logEvent(`Zoom sign-in failed: ${formatError(error)}`, "error");
+  } finally {
+    zoomSignInBtnEl.disabled = false;
+  }
+}
+
 async function initializeApp() {
   const query = <T extends HTMLElement>(id: string): T | null =>
     document.getElementById(id) as T | null;
@@ -2279,15 +3133,32 @@ async function initializeApp() {
   preflightScreenEl = query("preflight-screen");
   appContentEl = query("app-content");
   preflightStatusEl = query("preflight-status");
+  preflightInferenceTitleEl = query("preflight-inference-title");
   preflightPermissionsBtnEl = query<HTMLButtonElement>(
     "preflight-permissions-btn",
   );
   preflightRetryBtnEl = query<HTMLButtonElement>("preflight-retry-btn");
+  preflightCancelDownloadsBtnEl = query<HTMLButtonElement>(
+    "preflight-cancel-downloads-btn",
+  );
+  modelDownloadsEl = query("preflight-model-downloads");
   screenNamePreviewEl = query("screen-name-preview");
   wheelsContainerEl = query("screen-name-wheels");
   gestureStatusEl = query("gesture-status");
   activeWheelStatusEl = query("active-wheel-status");
   logContainerEl = query("event-log");
+  zoomSignInBtnEl = query<HTMLButtonElement>("zoom-sign-in-btn");
+  zoomAccountStatusEl = query("zoom-account-status");
+  profileSelectEl = query<HTMLSelectElement>("profile-select");
+  settingsFormEl = query<HTMLFormElement>("settings-form");
+  settingsFormStatusEl = query("settings-form-status");
+  nanobananaKeyInputEl = query<HTMLInputElement>("nanobanana-key-input");
+  nanobananaKeyStatusEl = query("nanobanana-key-status");
+  bundlePassphraseEl = query<HTMLInputElement>("settings-bundle-passphrase");
+  bundleStatusEl = query("settings-bundle-status");
+  importChangesEl = query("settings-import-changes");
+  importApplyBtnEl = query<HTMLButtonElement>("settings-import-apply-btn");
+  configEntriesEl = query("config-entries");
 
   const preflightLookup: Record<
     PreflightKey,
@@ -2298,10 +3169,10 @@ async function initializeApp() {
       status: "preflight-docker-status",
       message: "preflight-docker-message",
     },
-    dmr: {
-      row: "preflight-dmr",
-      status: "preflight-dmr-status",
-      message: "preflight-dmr-message",
+    inference: {
+      row: "preflight-inference",
+      status: "preflight-inference-status",
+      message: "preflight-inference-message",
     },
     models: {
       row: "preflight-models",
@@ -2328,14 +3199,64 @@ async function initializeApp() {
   preflightRetryBtnEl?.addEventListener("click", () => {
     void runPreflightChecks();
   });
+  preflightCancelDownloadsBtnEl?.addEventListener("click", () => {
+    void handleCancelModelDownloads();
+  });
+  zoomSignInBtnEl?.addEventListener("click", () => {
+    void handleZoomSignIn();
+  });
+  profileSelectEl?.addEventListener("change", () => {
+    void handleProfileChange();
+  });
+  settingsFormEl?.addEventListener("submit", (event) => {
+    event.preventDefault();
+    void handleSettingsSubmit();
+  });
+  for (const action of ["save", "test", "clear"] as const) {
+    query<HTMLButtonElement>(`nanobanana-key-${action}-btn`)?.addEventListener(
+      "click",
+      () => {
+        void handleNanobananaKey(action);
+      },
+    );
+  }
+
+  query("settings-export-btn")?.addEventListener("click", () => {
+    void handleSettingsExport();
+  });
+  query("settings-import-file")?.addEventListener("change", (event) => {
+    const input = event.currentTarget as HTMLInputElement;
+    const file = input.files?.[0];
+    input.value = "";
+    if (file) {
+      void handleSettingsImportFile(file);
+    }
+  });
+  importApplyBtnEl?.addEventListener("click", () => {
+    void handleSettingsImportApply();
+  });
+  query("describe-config-btn")?.addEventListener("click", () => {
+    void handleDescribeConfig();
+  });
 
   toggleAppVisibility(false);
 
+  await registerBackendEventListeners();
   initializeWheels();
   await loadSettings();
+  await loadProfiles();
   await loadPersistedWheelState();
 
-  logEvent("Companion initialised. Starting preflight checks…");
+  try {
+    const status = await invoke<AgentStatus>("get_agent_status");
+    logEvent(
+      `Companion initialised. Hub: ${status.hub?.baseUrl ?? "invalid Hub settings"} · Identity: ${status.identity}`,
+    );
+    renderZoomAccount(status.zoomAccount);
+  } catch (error) {
+    logEvent(`Companion configuration error: ${formatError(error)}`, "error");
+  }
+  logEvent("Starting preflight checks…");
   void runPreflightChecks();
 }
 
@@ -0,0 +1,117 @@
+// Minimal stand-in for Zoom's OAuth and users/me endpoints, for exercising the
+// companion's Zoom sign-in locally. Point the companion's zoomAuthorizeUrl,
+// zoomTokenUrl, and zoomUserUrl settings (and the Hub's ZOOM_API_BASE_URL) here.
+const crypto = require("crypto");
+const express = require("express");
+
+const port = Number(process.env.STANDIN_PORT || 4010);
+// Set low (e.g. 150) to exercise the companion's token refresh.
+const tokenLifetimeSecs = Number(process.env.STANDIN_TOKEN_LIFETIME_SECS || 3600);
+const user = {
+  id: process.env.STANDIN_ZOOM_USER_ID || "standin-user-1",
+  display_name: process.env.STANDIN_ZOOM_DISPLAY_NAME || "Stand-in User",
+  email: process.env.STANDIN_ZOOM_EMAIL || "standin@example.com",
+};
+
+const codes = new Map();
+const tokens = new Map();
+const refreshTokens = new Map();
+
+const app = express();
+app.use(express.urlencoded({ extended: false }));
+
+app.get("/oauth/authorize", (req, res) => {
+  const { redirect_uri, state, code_challenge, code_challenge_method } =
+    req.query;
+  if (!redirect_uri || !state) {
+    return res.status(400).send("redirect_uri and state are required.");
+  }
+
+  const code = crypto.randomBytes(16).toString("hex");
+  codes.set(code, {
+    redirectUri: redirect_uri,
+    codeChallenge: code_challenge,
+    codeChallengeMethod: code_challenge_method,
+  });
+  const target = new URL(redirect_uri);
+  target.searchParams.set("code", code);
+  target.searchParams.set("state", state);
+  console.log(
+    `[Stand-in] Authorized; redirecting to ${target.origin}${target.pathname}`,
+  );
+  res.redirect(target.toString());
+});
+
+function issueTokens(clientId) {
+  // Shaped like Zoom's JWT access tokens so the Hub can check which app it was issued to.
+  const claims = {
+    iss: `zm:cid:${clientId || "standin-client"}`,
+    jti: crypto.randomBytes(16).toString("hex"),
+  };
+  const accessToken = [
+    Buffer.from(JSON.stringify({ alg: "none" })).toString("base64url"),
+    Buffer.from(JSON.stringify(claims)).toString("base64url"),
+    "",
+  ].join(".");
+  const refreshToken = crypto.randomBytes(16).toString("hex");
+  tokens.set(accessToken, Date.now() + tokenLifetimeSecs * 1000);
+  refreshTokens.set(refreshToken, clientId);
+  return {
+    access_token: accessToken,
+    token_type: "bearer",
+    refresh_token: refreshToken,
+    expires_in: tokenLifetimeSecs,
+  };
+}
+
+app.post("/oauth/token", (req, res) => {
+  const { grant_type, code, redirect_uri, code_verifier, client_id } = req.body;
+  if (grant_type === "refresh_token") {
+    // Like Zoom, every refresh token is single-use.
+    const refreshToken = req.body.refresh_token;
+    if (!refreshTokens.has(refreshToken)) {
+      return res.status(400).json({ error: "invalid_grant" });
+    }
+    refreshTokens.delete(refreshToken);
+    console.log("[Stand-in] Refreshed an access token.");
+    return res.json(issueTokens(client_id));
+  }
+
+  const pending = codes.get(code);
+  codes.delete(code);
+  if (!pending || pending.redirectUri !== redirect_uri) {
+    return res.status(400).json({ error: "invalid_grant" });
+  }
+  if (pending.codeChallenge) {
+    const expected = crypto
+      .createHash("sha256")
+      .update(code_verifier || "")
+      .digest("base64url");
+    if (
+      pending.codeChallengeMethod !== "S256" ||
+      expected !== pending.codeChallenge
+    ) {
+      return res
+        .status(400)
+        .json({ error: "invalid_grant", reason: "PKCE mismatch" });
+    }
+  }
+
+  res.json(issueTokens(client_id));
+});
+
+app.get("/v2/users/me", (req, res) => {
+  const token = (req.get("authorization") || "").replace(/^Bearer\s+/i, "");
+  if (!(tokens.get(token) > Date.now())) {
+    return res
+      .status(401)
+      .json({ code: 124, message: "Invalid access token." });
+  }
+  res.json(user);
+});
+
+app.listen(port, () => {
+  console.log(
+    `[Stand-in] Zoom OAuth stand-in listening on http://127.0.0.1:${port}`,
+  );
+});
@@ -1,7 +1,9 @@
+const crypto = require("crypto");
 const express = require("express");
 const path = require("path");
 const { Firestore, Timestamp } = require("@google-cloud/firestore");
 const axios = require("axios");
+const { normalizeScreenName } = require("./screen_name");
 
 // --- Credentials ---
 const zoomClientId = process.env.ZOOM_CLIENT_ID;
@@ -33,6 +35,21 @@ const firestore = new Firestore();
 const AGENT_URL_TTL_MINUTES = 5;
 const BACKGROUND_PROXY_TIMEOUT_MS = 35_000;
 const BACKGROUND_PROXY_RETRIES = 3;
+const IDENTITY_SIGNATURE_MAX_SKEW_MS = 5 * 60_000;
+const IDENTITY_CLAIM_TTL_DAYS = 30;
+// Overridable so companions can be tested against a local stand-in OAuth server.
+const ZOOM_API_BASE_URL = (
+  process.env.ZOOM_API_BASE_URL || "https://api.zoom.us"
+).replace(/\/$/, "");
+// Zoom apps whose access tokens may bind a screen name; defaults to the Hub's own app.
+const ZOOM_TOKEN_CLIENT_IDS = (
+  process.env.ZOOM_TOKEN_CLIENT_IDS ||
+  zoomClientId ||
+  ""
+)
+  .split(",")
+  .map((id) => id.trim())
+  .filter(Boolean);
 
 app.use(express.json());
 app.use(express.static(__dirname));
@@ -51,11 +68,139 @@ app.use((req, res, next) => {
 });
 
 // --- API Endpoints ---
-function normalizeScreenName(input) {
-  if (typeof input !== "string") {
-    return "";
+
+// Returns an error message, or null when `body` carries a valid Ed25519 signature
+// over the same message the companion builds in identity.rs that has not been used before.
+async function verifyAgentSignature(action, body) {
+  const { screenName, tunnelUrl = "", publicKey, timestampMs, signature } =
+    body;
+  if (
+    typeof publicKey !== "string" ||
+    typeof signature !== "string" ||
+    !Number.isFinite(timestampMs)
+  ) {
+    return "publicKey, timestampMs, and signature are required. Update the companion app.";
+  }
+  if (Math.abs(Date.now() - timestampMs) > IDENTITY_SIGNATURE_MAX_SKEW_MS) {
+    return "Signature timestamp is too far from the Hub clock. Check the system clock.";
+  }
+
+  const rawKey = Buffer.from(publicKey, "base64");
+  if (rawKey.length !== 32) {
+    return "publicKey is not a valid Ed25519 key.";
+  }
+  let key;
+  try {
+    key = crypto.createPublicKey({
+      key: { kty: "OKP", crv: "Ed25519", x: rawKey.toString("base64url") },
+      format: "jwk",
+    });
+  } catch (error) {
+    return "publicKey is not a valid Ed25519 key.";
   }
-  return input.trim().toLowerCase().replace(/\s+/g, " ");
+
+  const message = `slowly-unhinged:${action}\n${screenName}\n${tunnelUrl}\n${timestampMs}`;
+  const valid = crypto.verify(
+    null,
+    Buffer.from(message),
+    key,
+    Buffer.from(signature, "base64"),
+  );
+  if (!valid) {
+    return "Signature verification failed.";
+  }
+  return (await consumeSignature(publicKey, signature))
+    ? null
+    : "This signed request has already been used.";
+}
+
+// Records a signature so a captured request cannot be replayed while its timestamp is
+// still within the allowed skew. Ed25519 signatures are deterministic, so the same
+// message always produces the same signature. Returns false when it was already used.
+// A Firestore TTL policy on `expiresAt` clears entries once the skew check rejects them.
+async function consumeSignature(publicKey, signature) {
+  const id = crypto
+    .createHash("sha256")
+    .update(`${publicKey}:${signature}`)
+    .digest("hex");
+  try {
+    await firestore
+      .collection("usedSignatures")
+      .doc(id)
+      .create({
+        expiresAt: Timestamp.fromMillis(
+          Date.now() + 2 * IDENTITY_SIGNATURE_MAX_SKEW_MS,
+        ),
+      });
+    return true;
+  } catch (error) {
+    // gRPC ALREADY_EXISTS
+    if (error.code === 6) {
+      return false;
+    }
+    throw error;
+  }
+}
+
+// Screen names stay bound to the first key that claimed them until the owner
+// releases the claim or it goes unused for IDENTITY_CLAIM_TTL_DAYS.
+// Must run inside `transaction` so the claim cannot change before the caller writes it.
+async function getIdentityClaim(normalizedScreenName, transaction) {
+  const claimRef = firestore
+    .collection("identities")
+    .doc(normalizedScreenName);
+  const doc = await transaction.get(claimRef);
+  if (!doc.exists) {
+    return { claimRef, publicKey: null };
+  }
+
+  const { publicKey, lastSeenAt, zoomUserId = null } = doc.data() || {};
+  const cutoff = Timestamp.now().seconds - IDENTITY_CLAIM_TTL_DAYS * 86_400;
+  if (!publicKey || !lastSeenAt || lastSeenAt.seconds < cutoff) {
+    console.log(
+      `[Hub] Identity claim for "${normalizedScreenName}" has lapsed. Deleting.`,
+    );
+    transaction.delete(claimRef);
+    return { claimRef, publicKey: null };
+  }
+  return { claimRef, publicKey, zoomUserId };
+}
+
+// Zoom access tokens are JWTs whose issuer names the app they were issued to
+// ("zm:cid:<client id>"). The payload is only trusted once Zoom accepts the token below.
+function zoomTokenClientId(accessToken) {
+  const [, payload] = String(accessToken).split(".");
+  if (!payload) {
+    return null;
+  }
+  try {
+    const { iss } = JSON.parse(Buffer.from(payload, "base64url").toString());
+    return typeof iss === "string" && iss.startsWith("zm:cid:")
+      ? iss.slice("zm:cid:".length)
+      : null;
+  } catch (error) {
+    return null;
+  }
+}
+
+// Resolves a companion-supplied Zoom access token to the Zoom user it belongs to,
+// refusing tokens issued to any Zoom app other than ours.
+async function verifyZoomAccessToken(accessToken) {
+  const clientId = zoomTokenClientId(accessToken);
+  if (!clientId || !ZOOM_TOKEN_CLIENT_IDS.includes(clientId)) {
+    throw new Error(
+      `Access token was issued to ${clientId ? `Zoom app ${clientId}` : "an unknown Zoom app"}.`,
+    );
+  }
+  const response = await axios.get(`${ZOOM_API_BASE_URL}/v2/users/me`, {
+    headers: { Authorization: `Bearer ${accessToken}` },
+    timeout: 10_000,
+  });
+  const zoomUserId = response.data?.id;
+  if (!zoomUserId) {
+    throw new Error("Zoom user profile did not include an id.");
+  }
+  return String(zoomUserId);
 }
 
 function hasDefaultNanobananaKey() {
@@ -164,7 +309,11 @@ async function getActiveAgent(normalizedScreenName) {
     }
   }
 
-  return { tunnelUrl };
+  return {
+    tunnelUrl,
+    publicKey: data.publicKey,
+    zoomUserId: data.zoomUserId || null,
+  };
 }
 
 app.post("/api/register-agent", async (req, res) => {
@@ -182,23 +331,92 @@ app.post("/api/register-agent", async (req, res) => {
       .json({ error: "screenName and tunnelUrl are required." });
   }
 
+  const { publicKey, zoomAccessToken } = req.body;
+
   try {
-    console.log(
-      `[Hub] Registering agent for "${screenName}" (normalized: "${normalizedScreenName}") at ${tunnelUrl}`,
-    );
+    const signatureError = await verifyAgentSignature("register", req.body);
+    if (signatureError) {
+      return res.status(401).json({ error: signatureError });
+    }
+
+    // Verified before the transaction, which may be retried and must not make network calls.
+    let verifiedZoomUserId = null;
+    if (zoomAccessToken) {
+      try {
+        verifiedZoomUserId = await verifyZoomAccessToken(zoomAccessToken);
+      } catch (verifyError) {
+        console.warn(
+          `[Hub] Zoom sign-in for "${normalizedScreenName}" could not be verified:`,
+          verifyError.message,
+        );
+        return res
+          .status(401)
+          .json({ error: "Zoom sign-in could not be verified." });
+      }
+    }
+
     const agentRef = firestore.collection("agents").doc(normalizedScreenName);
-    const now = Timestamp.now();
     const needsDefaultNanobananaKey =
       Boolean(requiresNanobananaKey) && !hasLocalNanobananaKey;
-    await agentRef.set({
-      tunnelUrl,
-      registeredAt: now,
-      lastSeenAt: now,
-      screenNameOriginal: screenName,
-      requiresNanobananaKey: Boolean(requiresNanobananaKey),
-      hasLocalNanobananaKey: Boolean(hasLocalNanobananaKey),
-      usesHubNanobananaKey: needsDefaultNanobananaKey,
+    // Reading and writing the claim in one transaction keeps two registrations from
+    // both seeing an unclaimed name and binding it to different keys.
+    const rejection = await firestore.runTransaction(async (transaction) => {
+      const claim = await getIdentityClaim(normalizedScreenName, transaction);
+
+      // A fresh Zoom sign-in proves the account; re-registrations without one
+      // inherit the account already verified for this identity.
+      let zoomUserId = claim.publicKey === publicKey ? claim.zoomUserId : null;
+      if (verifiedZoomUserId) {
+        if (claim.zoomUserId && claim.zoomUserId !== verifiedZoomUserId) {
+          return "This screen name is bound to a different Zoom account.";
+        }
+        zoomUserId = verifiedZoomUserId;
+      }
+
+      // The same verified Zoom account may move the name to a new key.
+      const sameZoomAccount =
+        Boolean(zoomUserId) && claim.zoomUserId === zoomUserId;
+      if (
+        claim.publicKey &&
+        claim.publicKey !== publicKey &&
+        !sameZoomAccount
+      ) {
+        console.warn(
+          `[Hub] Refusing to bind "${normalizedScreenName}" to a different identity.`,
+        );
+        return "This screen name is bound to a different agent identity.";
+      }
+
+      console.log(
+        `[Hub] Registering agent for "${screenName}" (normalized: "${normalizedScreenName}") at ${tunnelUrl}`,
+      );
+      const now = Timestamp.now();
+      transaction.set(
+        claim.claimRef,
+        {
+          publicKey,
+          zoomUserId,
+          lastSeenAt: now,
+          ...(claim.publicKey === publicKey ? {} : { boundAt: now }),
+        },
+        { merge: true },
+      );
+      transaction.set(agentRef, {
+        tunnelUrl,
+        publicKey,
+        zoomUserId,
+        registeredAt: now,
+        lastSeenAt: now,
+        screenNameOriginal: screenName,
+        requiresNanobananaKey: Boolean(requiresNanobananaKey),
+        hasLocalNanobananaKey: Boolean(hasLocalNanobananaKey),
+        usesHubNanobananaKey: needsDefaultNanobananaKey,
+      });
+      return null;
     });
+    if (rejection) {
+      return res.status(409).json({ error: rejection });
+    }
 
     if (needsDefaultNanobananaKey) {
       try {
@@ -233,7 +451,7 @@ app.post("/api/register-agent", async (req, res) => {
 });
 
 app.post("/api/unregister-agent", async (req, res) => {
-  const { screenName } = req.body;
+  const { screenName, tunnelUrl } = req.body;
   const normalizedScreenName = normalizeScreenName(screenName);
 
   if (!normalizedScreenName) {
@@ -241,10 +459,30 @@ app.post("/api/unregister-agent", async (req, res) => {
   }
 
   try {
+    const signatureError = await verifyAgentSignature("unregister", req.body);
+    if (signatureError) {
+      return res.status(401).json({ error: signatureError });
+    }
+
     console.log(
       `[Hub] Unregistering agent for "${normalizedScreenName}" (original: "${screenName}")`,
     );
     const agentRef = firestore.collection("agents").doc(normalizedScreenName);
+    const doc = await agentRef.get();
+    const registered = doc.exists ? doc.data() || {} : {};
+    if (registered.publicKey && registered.publicKey !== req.body.publicKey) {
+      return res.status(403).json({
+        error: "This registration belongs to a different agent identity.",
+      });
+    }
+    if (tunnelUrl) {
+      const registeredUrl = registered.tunnelUrl;
+      if (registeredUrl && registeredUrl !== tunnelUrl) {
+        return res.status(409).json({
+          error: "A different tunnel is registered for this screen name.",
+        });
+      }
+    }
     await agentRef.delete();
     res.status(200).json({ message: "Agent unregistered successfully." });
   } catch (error) {
@@ -256,6 +494,99 @@ app.post("/api/unregister-agent", async (req, res) => {
   }
 });
 
+app.post("/api/agent-heartbeat", async (req, res) => {
+  const { screenName, tunnelUrl } = req.body;
+  const normalizedScreenName = normalizeScreenName(screenName);
+
+  if (!normalizedScreenName || !tunnelUrl) {
+    return res
+      .status(400)
+      .json({ error: "screenName and tunnelUrl are required." });
+  }
+
+  try {
+    const signatureError = await verifyAgentSignature("heartbeat", req.body);
+    if (signatureError) {
+      return res.status(401).json({ error: signatureError });
+    }
+
+    const agent = await getActiveAgent(normalizedScreenName);
+
+    if (
+      !agent ||
+      agent.tunnelUrl !== tunnelUrl ||
+      agent.publicKey !== req.body.publicKey
+    ) {
+      return res
+        .status(404)
+        .json({ error: "No active registration found for this agent." });
+    }
+
+    await firestore
+      .collection("agents")
+      .doc(normalizedScreenName)
+      .update({ lastSeenAt: Timestamp.now() });
+    res.status(200).json({ message: "Heartbeat recorded." });
+  } catch (error) {
+    console.error(
+      `[Hub] Error recording heartbeat for "${normalizedScreenName}":`,
+      error,
+    );
+    res.status(500).json({ error: "Failed to record heartbeat." });
+  }
+});
+
+app.post("/api/release-identity", async (req, res) => {
+  const { screenName, publicKey } = req.body;
+  const normalizedScreenName = normalizeScreenName(screenName);
+
+  if (!normalizedScreenName) {
+    return res.status(400).json({ error: "screenName is required." });
+  }
+
+  try {
+    const signatureError = await verifyAgentSignature("release", req.body);
+    if (signatureError) {
+      return res.status(401).json({ error: signatureError });
+    }
+
+    const rejection = await firestore.runTransaction(async (transaction) => {
+      const claim = await getIdentityClaim(normalizedScreenName, transaction);
+      if (!claim.publicKey) {
+        return {
+          status: 404,
+          error: "No identity is bound to this screen name.",
+        };
+      }
+      if (claim.publicKey !== publicKey) {
+        return {
+          status: 403,
+          error: "This screen name is bound to a different agent identity.",
+        };
+      }
+
+      console.log(
+        `[Hub] Releasing identity claim for "${normalizedScreenName}".`,
+      );
+      transaction.delete(
+        firestore.collection("agents").doc(normalizedScreenName),
+      );
+      transaction.delete(claim.claimRef);
+      return null;
+    });
+    if (rejection) {
+      return res.status(rejection.status).json({ error: rejection.error });
+    }
+    res.status(200).json({ message: "Identity released." });
+  } catch (error) {
+    console.error(
+      `[Hub] Error releasing identity for "${normalizedScreenName}":`,
+      error,
+    );
+    res.status(500).json({ error: "Failed to release identity." });
+  }
+});
+
 app.get("/api/get-agent-url", async (req, res) => {
   const { screenName } = req.query;
   const normalizedScreenName = normalizeScreenName(screenName);
@@ -281,7 +612,10 @@ app.get("/api/get-agent-url", async (req, res) => {
     console.log(
       `[Hub] Found agent for "${normalizedScreenName}" at ${agent.tunnelUrl}`,
     );
-    res.status(200).json({ tunnelUrl: agent.tunnelUrl });
+    res.status(200).json({
+      tunnelUrl: agent.tunnelUrl,
+      zoomVerified: Boolean(agent.zoomUserId),
+    });
   } catch (error) {
     console.error(
       `[Hub] Error getting agent URL for "${normalizedScreenName}":`,
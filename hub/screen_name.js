@@ -0,0 +1,19 @@
+// Shared by the Hub (index.js) and the Zoom app page (zoom_app.js). Mirrors
+// ScreenName::parse in the companion (companion-app/src-tauri/src/screen_name.rs);
+// test/screen_name_vectors.json checks that both give the same identity.
+function normalizeScreenName(input) {
+  if (typeof input !== "string") {
+    return "";
+  }
+  return input
+    .normalize("NFKC")
+    .replace(/\s+/g, " ")
+    .trim()
+    .toUpperCase()
+    .toLowerCase()
+    .normalize("NFKC");
+}
+
+if (typeof module !== "undefined") {
+  module.exports = { normalizeScreenName };
+}
@@ -0,0 +1,17 @@
+// Runs the vectors shared with the companion's screen_name.rs tests against the Hub's copy.
+const assert = require("node:assert");
+const test = require("node:test");
+
+const { normalizeScreenName } = require("../screen_name");
+const vectors = require("./screen_name_vectors.json");
+
+for (const vector of vectors) {
+  // Only the companion validates names; the Hub normalizes whatever it is sent, so
+  // rejected inputs have no identity to compare.
+  if (vector.error) {
+    continue;
+  }
+  test(vector.name, () => {
+    assert.strictEqual(normalizeScreenName(vector.input), vector.normalized);
+  });
+}
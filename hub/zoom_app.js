@@ -19,13 +19,6 @@ function logMessage(message, level = "info") {
   activityLogEl.prepend(entry);
 }
 
-function normalizeScreenName(input) {
-  if (typeof input !== "string") {
-    return "";
-  }
-  return input.trim().toLowerCase().replace(/\s+/g, " ");
-}
-
 function updateScreenNameDisplay(text) {
   if (!screenNameEl) {
     return;